@@ -0,0 +1,119 @@
+//! A stand-in game for trying out the `Memory` autosplitter without a real game.
+//!
+//! ```bash
+//! $ cargo run --example memory_dummy -- dummy-splits.json
+//! ```
+//!
+//! This writes a splits file whose autosplitter reads this process, then takes commands on
+//! stdin: `level <n>` sets the level (1 starts the run, every increase splits, 0 resets), and
+//! `loading <0|1>` starts and ends a load. Run `flitter dummy-splits.json` or
+//! `flitter autosplitter-dry-run dummy-splits.json` in another terminal to follow along.
+
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+
+// Read directly, at an offset from the start of the executable
+#[cfg(target_os = "linux")]
+static LEVEL: AtomicU32 = AtomicU32::new(0);
+// Read through a pointer, like most game state
+#[cfg(target_os = "linux")]
+static STATE: AtomicPtr<State> = AtomicPtr::new(std::ptr::null_mut());
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct State {
+    _padding: u64,
+    loading: AtomicU32,
+}
+
+#[cfg(target_os = "linux")]
+fn main() -> anyhow::Result<()> {
+    use std::io::BufRead;
+
+    use anyhow::{anyhow, Context};
+    use serde_json::json;
+
+    let path = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("Usage: memory_dummy <splits file to write>"))?;
+
+    let state: &'static State = Box::leak(Box::new(State {
+        _padding: 0,
+        loading: AtomicU32::new(0),
+    }));
+    STATE.store(state as *const State as *mut State, Ordering::SeqCst);
+
+    // Offsets change with every build, so the splits file is written to match this one
+    let base = exe_base()?;
+    let level_offset = &LEVEL as *const AtomicU32 as u64 - base;
+    let state_offset = &STATE as *const AtomicPtr<State> as u64 - base;
+    let process = std::fs::read_to_string("/proc/self/comm")?;
+
+    let splits_file = json!({
+        "version": 4,
+        "title": "Memory Dummy",
+        "category": "Any%",
+        "attempts": 0,
+        "completed": 0,
+        "split_names": ["Level 1", "Level 2", "Level 3"],
+        "golds": [null, null, null],
+        "personal_best": null,
+        "attempt_history": [],
+        "resets": [0, 0, 0],
+        "completed_before_resets": 0,
+        "segment_history": [[], [], []],
+        "autosplitter": {
+            "type": "Memory",
+            "process": process.trim_end(),
+            "values": {
+                "level": { "offsets": [format!("{:#x}", level_offset)], "type": "u32" },
+                "loading": {
+                    "offsets": [format!("{:#x}", state_offset), "0x8"],
+                    "type": "u32"
+                }
+            },
+            "start": [{ "value": "level", "when": { "changed_to": 1 } }],
+            "split": [{ "value": "level", "when": "increased" }],
+            "reset": [{ "value": "level", "when": { "changed_to": 0 } }],
+            "loading": [{ "value": "loading", "when": { "equals": 1 } }]
+        }
+    });
+    std::fs::write(&path, serde_json::to_string_pretty(&splits_file)?)
+        .with_context(|| format!("Failed to write {}", path))?;
+    println!("Wrote {}, waiting for commands", path);
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let Some((name, value)) = line.split_once(' ') else {
+            println!("Commands are `level <n>` and `loading <0|1>`");
+            continue;
+        };
+        let Ok(value) = value.trim().parse() else {
+            println!("Not a number: {}", value);
+            continue;
+        };
+        match name {
+            "level" => LEVEL.store(value, Ordering::SeqCst),
+            "loading" => state.loading.store(value, Ordering::SeqCst),
+            _ => println!("Unknown value: {}", name),
+        }
+    }
+    Ok(())
+}
+
+// Where the executable is mapped, which the autosplitter counts offsets from
+#[cfg(target_os = "linux")]
+fn exe_base() -> anyhow::Result<u64> {
+    let exe = std::fs::read_link("/proc/self/exe")?;
+    let maps = std::fs::read_to_string("/proc/self/maps")?;
+    maps.lines()
+        .filter(|line| line.ends_with(exe.to_string_lossy().as_ref()))
+        .filter_map(|line| u64::from_str_radix(line.split('-').next()?, 16).ok())
+        .min()
+        .ok_or_else(|| anyhow::anyhow!("Executable isn't mapped"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("Memory autosplitters only work on Linux");
+}
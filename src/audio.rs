@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+use crate::events::{split_events, EventKind, EventListener, TimerEvent};
+use crate::timer_state::TimerState;
+
+// Command line players tried in order, each taking the file to play as its only argument
+static PLAYERS: &[&str] = &["afplay", "paplay", "aplay"];
+
+pub trait AudioBackend {
+    /// Starts playing a sound file without waiting for it to finish.
+    fn play(&mut self, path: &Path) -> anyhow::Result<()>;
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AudioBackendName {
+    // The first command line player found on the system
+    System,
+    None,
+}
+
+/// Plays sounds with a command line player.
+pub struct CommandBackend {
+    player: PathBuf,
+}
+
+impl CommandBackend {
+    pub fn find() -> Option<Self> {
+        let paths = std::env::var_os("PATH")?;
+        PLAYERS.iter().find_map(|player| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(player))
+                .find(|path| path.is_file())
+                .map(|player| Self { player })
+        })
+    }
+}
+
+impl AudioBackend for CommandBackend {
+    fn play(&mut self, path: &Path) -> anyhow::Result<()> {
+        let mut child = Command::new(&self.player)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// Plays nothing, for when there's no audio.
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, _: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// The backend to play sounds with. Failing to find a player is an error rather than silence,
+/// since this is only asked for when sounds are set up.
+pub fn get_backend(name: AudioBackendName) -> anyhow::Result<Box<dyn AudioBackend>> {
+    match name {
+        AudioBackendName::System => match CommandBackend::find() {
+            Some(backend) => Ok(Box::new(backend)),
+            None => Err(anyhow!(
+                "Sounds are set up but no audio player was found (tried {}), set audio_backend to None to turn sounds off",
+                PLAYERS.join(", ")
+            )),
+        },
+        AudioBackendName::None => Ok(Box::new(NullBackend)),
+    }
+}
+
+// When one split causes several events, only the sound for the first of these is played
+static CUE_PRIORITY: &[EventKind] = &[
+    EventKind::PbAchieved,
+    EventKind::Finished,
+    EventKind::GoldAchieved,
+    EventKind::PbPaceLost,
+    EventKind::Split,
+];
+
+/// Plays the sound file configured for each kind of event.
+pub struct SoundCues {
+    sounds: HashMap<EventKind, PathBuf>,
+    backend: Box<dyn AudioBackend>,
+}
+
+impl SoundCues {
+    pub fn new(
+        sounds: HashMap<EventKind, PathBuf>,
+        backend: Box<dyn AudioBackend>,
+    ) -> anyhow::Result<Self> {
+        // Catch typos now rather than silently missing a cue mid-run
+        if let Some(missing) = sounds.values().find(|path| !path.is_file()) {
+            return Err(anyhow!("Sound file {} does not exist", missing.display()));
+        }
+        Ok(Self { sounds, backend })
+    }
+
+    fn cue_for(&self, event: &TimerEvent, timer: &TimerState) -> Option<&PathBuf> {
+        let kind = event.kind();
+        if !CUE_PRIORITY.contains(&kind) {
+            return self.sounds.get(&kind);
+        }
+
+        let split_kinds: Vec<EventKind> = split_events(timer).iter().map(|e| e.kind()).collect();
+        let played = CUE_PRIORITY
+            .iter()
+            .find(|kind| split_kinds.contains(kind) && self.sounds.contains_key(kind))?;
+        if *played == kind {
+            self.sounds.get(&kind)
+        } else {
+            None
+        }
+    }
+}
+
+impl EventListener for SoundCues {
+    fn on_event(&mut self, event: &TimerEvent, timer: &TimerState) -> anyhow::Result<()> {
+        if let Some(path) = self.cue_for(event, timer).cloned() {
+            self.backend
+                .play(&path)
+                .with_context(|| format!("Failed to play sound {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
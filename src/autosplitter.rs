@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::split_file::SplitFile;
+use crate::timer_state::TimerState;
+use log_file::{LogFile, Progress};
+use memory::MemoryConfig;
+
+mod log_file;
+mod memory;
+mod script;
+mod sources;
+
+/// How often autosplitters check the game.
+pub static TICK_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// Which autosplitter a splits file uses, and how it's set up. Paths are relative to the
+/// splits file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum AutosplitterConfig {
+    Script {
+        path: PathBuf,
+    },
+    /// Regexes matched against each new line of the log. There's one split pattern per split,
+    /// `null` for splits done by hand.
+    LogFile {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        start: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reset: Option<String>,
+        splits: Vec<Option<String>>,
+    },
+    /// Values read from the game's memory, and conditions on them. Linux only.
+    Memory(MemoryConfig),
+}
+
+impl AutosplitterConfig {
+    /// The per-split patterns, which have to stay lined up with the splits.
+    pub fn split_patterns(&self) -> Option<&Vec<Option<String>>> {
+        match self {
+            AutosplitterConfig::LogFile { splits, .. } => Some(splits),
+            _ => None,
+        }
+    }
+
+    pub fn split_patterns_mut(&mut self) -> Option<&mut Vec<Option<String>>> {
+        match self {
+            AutosplitterConfig::LogFile { splits, .. } => Some(splits),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AutosplitterCommand {
+    Start,
+    Split,
+    Reset,
+    PauseGameTime,
+    ResumeGameTime,
+}
+
+pub trait Autosplitter {
+    /// Checks the game, returning what the timer should do.
+    fn update(&mut self, timer: &TimerState) -> anyhow::Result<Vec<AutosplitterCommand>>;
+}
+
+pub fn load_autosplitter(
+    config: &AutosplitterConfig,
+    split_file: &SplitFile,
+) -> anyhow::Result<Box<dyn Autosplitter>> {
+    match config {
+        AutosplitterConfig::Script { path } => {
+            let script = script::Script::load(&relative_to(split_file, path))?;
+            Ok(Box::new(script))
+        }
+        AutosplitterConfig::LogFile { .. } => Ok(Box::new(open_log_file(split_file, false)?)),
+        AutosplitterConfig::Memory(config) => memory::load(config),
+    }
+}
+
+/// Follows the game without running the timer, printing what the autosplitter would do. Runs
+/// until interrupted.
+pub fn dry_run(split_file: &SplitFile, from_start: bool) -> anyhow::Result<()> {
+    match &split_file.autosplitter {
+        Some(AutosplitterConfig::LogFile { path, .. }) => {
+            println!(
+                "Watching {}, press Ctrl-C to stop",
+                relative_to(split_file, path).display()
+            );
+            dry_run_log_file(split_file, from_start)
+        }
+        Some(AutosplitterConfig::Memory(config)) => memory::dry_run(config),
+        Some(AutosplitterConfig::Script { .. }) => {
+            Err(anyhow!("Script autosplitters have no dry run"))
+        }
+        None => Err(anyhow!("Splits file has no autosplitter")),
+    }
+}
+
+// Prints each line of the log the autosplitter would act on
+fn dry_run_log_file(split_file: &SplitFile, from_start: bool) -> anyhow::Result<()> {
+    let log = open_log_file(split_file, from_start)?;
+
+    let mut progress = Progress::NotStarted;
+    loop {
+        for line in log.new_lines() {
+            let Some(command) = log.match_line(&line, progress) else {
+                continue;
+            };
+            let action = match (command, progress) {
+                (AutosplitterCommand::Split, Progress::AtSplit(i)) => {
+                    format!("Split {} ({})", i + 1, split_file.split_names[i])
+                }
+                _ => format!("{:?}", command),
+            };
+            println!("{}: {}", action, line);
+            progress = log.advance(progress, command);
+
+            // Nobody is there to do splits by hand, so assume they happen straight away
+            while let Progress::AtSplit(i) = progress
+                && log.is_manual(i)
+            {
+                println!("Split {} ({}): by hand", i + 1, split_file.split_names[i]);
+                progress = log.advance(progress, AutosplitterCommand::Split);
+            }
+        }
+        thread::sleep(TICK_INTERVAL);
+    }
+}
+
+fn open_log_file(split_file: &SplitFile, from_start: bool) -> anyhow::Result<LogFile> {
+    match &split_file.autosplitter {
+        Some(AutosplitterConfig::LogFile {
+            path,
+            start,
+            reset,
+            splits,
+        }) => LogFile::open(
+            &relative_to(split_file, path),
+            start.as_deref(),
+            reset.as_deref(),
+            splits,
+            from_start,
+        ),
+        _ => Err(anyhow!("Splits file has no log file autosplitter")),
+    }
+}
+
+fn relative_to(split_file: &SplitFile, path: &Path) -> PathBuf {
+    split_file
+        .path()
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(path)
+}
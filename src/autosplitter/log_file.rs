@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use anyhow::Context;
+use regex::Regex;
+
+use super::sources::LineSource;
+use super::{Autosplitter, AutosplitterCommand};
+use crate::timer_state::{TimerMode, TimerState};
+
+/// How far into a run the log has got, as far as the log file autosplitter can tell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Progress {
+    NotStarted,
+    AtSplit(usize),
+    Finished,
+}
+
+/// Follows a game's log file, starting, splitting and resetting when lines match the
+/// configured patterns.
+pub struct LogFile {
+    source: LineSource,
+    start: Option<Regex>,
+    reset: Option<Regex>,
+    splits: Vec<Option<Regex>>,
+}
+
+impl LogFile {
+    pub fn open(
+        path: &Path,
+        start: Option<&str>,
+        reset: Option<&str>,
+        splits: &[Option<String>],
+        from_start: bool,
+    ) -> anyhow::Result<Self> {
+        let splits = splits
+            .iter()
+            .enumerate()
+            .map(|(i, pattern)| {
+                pattern
+                    .as_deref()
+                    .map(|pattern| compile(pattern, &format!("split {}", i + 1)))
+                    .transpose()
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            source: LineSource::tail_file(path, from_start),
+            start: start.map(|pattern| compile(pattern, "start")).transpose()?,
+            reset: reset.map(|pattern| compile(pattern, "reset")).transpose()?,
+            splits,
+        })
+    }
+
+    /// Lines written to the log since the last call.
+    pub fn new_lines(&self) -> Vec<String> {
+        self.source.new_lines()
+    }
+
+    /// What a line means for a run that has got this far, if anything.
+    pub fn match_line(&self, line: &str, progress: Progress) -> Option<AutosplitterCommand> {
+        let matches = |regex: &Option<Regex>| regex.as_ref().is_some_and(|re| re.is_match(line));
+
+        if progress != Progress::NotStarted && matches(&self.reset) {
+            return Some(AutosplitterCommand::Reset);
+        }
+        match progress {
+            Progress::NotStarted if matches(&self.start) => Some(AutosplitterCommand::Start),
+            Progress::AtSplit(i) if matches(&self.splits[i]) => Some(AutosplitterCommand::Split),
+            _ => None,
+        }
+    }
+
+    /// Whether a split is done by hand rather than by a pattern.
+    pub fn is_manual(&self, split: usize) -> bool {
+        self.splits[split].is_none()
+    }
+
+    /// How far the run has got after a line matched.
+    pub fn advance(&self, progress: Progress, command: AutosplitterCommand) -> Progress {
+        match (command, progress) {
+            (AutosplitterCommand::Start, _) => Progress::AtSplit(0),
+            (AutosplitterCommand::Split, Progress::AtSplit(i)) if i + 1 < self.splits.len() => {
+                Progress::AtSplit(i + 1)
+            }
+            (AutosplitterCommand::Split, _) => Progress::Finished,
+            (AutosplitterCommand::Reset, _) => Progress::NotStarted,
+            _ => progress,
+        }
+    }
+}
+
+impl Autosplitter for LogFile {
+    fn update(&mut self, timer: &TimerState) -> anyhow::Result<Vec<AutosplitterCommand>> {
+        let mut progress = match timer.mode {
+            TimerMode::Initial => Progress::NotStarted,
+            TimerMode::Running { .. } | TimerMode::Paused { .. } => {
+                Progress::AtSplit(timer.splits.len())
+            }
+            TimerMode::Finished { .. } => Progress::Finished,
+        };
+
+        // Several lines can arrive at once, so later ones are matched against where the earlier
+        // ones will have taken the run
+        let mut commands = Vec::new();
+        for line in self.new_lines() {
+            if let Some(command) = self.match_line(&line, progress) {
+                progress = self.advance(progress, command);
+                commands.push(command);
+            }
+        }
+        Ok(commands)
+    }
+}
+
+fn compile(pattern: &str, what: &str) -> anyhow::Result<Regex> {
+    Regex::new(pattern)
+        .with_context(|| format!("Invalid {} pattern in log file autosplitter", what))
+}
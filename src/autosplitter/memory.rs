@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+mod process;
+
+/// How a memory autosplitter finds the game and what it reads from it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemoryConfig {
+    /// Name of the game's process, or of its executable
+    pub process: String,
+    #[serde(default = "default_pointer_size")]
+    pub pointer_size: u8,
+    pub values: BTreeMap<String, PointerPath>,
+    #[serde(default)]
+    pub start: Vec<Condition>,
+    #[serde(default)]
+    pub split: Vec<Condition>,
+    #[serde(default)]
+    pub reset: Vec<Condition>,
+    #[serde(default)]
+    pub loading: Vec<Condition>,
+}
+
+fn default_pointer_size() -> u8 {
+    8
+}
+
+/// Where a value is: the first offset is from the start of the module, and every offset after it
+/// is from the pointer read at the previous address.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PointerPath {
+    /// File name of the module, the executable if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    pub offsets: Vec<Offset>,
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+}
+
+/// Offsets are usually written in hex, which JSON numbers can't be.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Offset {
+    Number(i64),
+    Text(String),
+}
+
+impl Offset {
+    fn value(&self) -> anyhow::Result<i64> {
+        match self {
+            Offset::Number(n) => Ok(*n),
+            Offset::Text(text) => {
+                let (negative, digits) = match text.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, text.as_str()),
+                };
+                let n = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => digits.parse(),
+                }
+                .map_err(|_| anyhow!("Invalid offset: {}", text))?;
+                Ok(if negative { -n } else { n })
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValueType {
+    fn size(self) -> usize {
+        match self {
+            ValueType::U8 | ValueType::I8 => 1,
+            ValueType::U16 | ValueType::I16 => 2,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 4,
+            ValueType::U64 | ValueType::I64 | ValueType::F64 => 8,
+        }
+    }
+
+    // Values are little endian, as on every platform Flitter runs on
+    fn decode(self, bytes: &[u8]) -> f64 {
+        match self {
+            ValueType::U8 => bytes[0] as f64,
+            ValueType::I8 => bytes[0] as i8 as f64,
+            ValueType::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::U64 => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::I64 => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ValueType::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+/// A test of one value, comparing it with what it was on the previous frame for changes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Condition {
+    pub value: String,
+    pub when: Test,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Test {
+    Equals(f64),
+    NotEquals(f64),
+    ChangedTo(f64),
+    ChangedFrom(f64),
+    Changed,
+    Increased,
+    Decreased,
+}
+
+impl Test {
+    // Values that couldn't be read never pass
+    fn check(self, old: Option<f64>, current: Option<f64>) -> bool {
+        let Some(current) = current else {
+            return false;
+        };
+        match self {
+            Test::Equals(x) => current == x,
+            Test::NotEquals(x) => current != x,
+            Test::ChangedTo(x) => current == x && old.is_some_and(|old| old != x),
+            Test::ChangedFrom(x) => old == Some(x) && current != x,
+            Test::Changed => old.is_some_and(|old| old != current),
+            Test::Increased => old.is_some_and(|old| current > old),
+            Test::Decreased => old.is_some_and(|old| current < old),
+        }
+    }
+}
+
+impl MemoryConfig {
+    // Catches mistakes before the game is even running
+    fn validate(&self) -> anyhow::Result<()> {
+        if ![4, 8].contains(&self.pointer_size) {
+            return Err(anyhow!("Pointer size must be 4 or 8 bytes"));
+        }
+        for (name, path) in &self.values {
+            if path.offsets.is_empty() {
+                return Err(anyhow!("Value {} has no offsets", name));
+            }
+            for offset in &path.offsets {
+                offset.value()?;
+            }
+        }
+
+        let conditions = [&self.start, &self.split, &self.reset, &self.loading];
+        for condition in conditions.into_iter().flatten() {
+            if !self.values.contains_key(&condition.value) {
+                return Err(anyhow!(
+                    "Condition uses value {}, which isn't defined",
+                    condition.value
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Values on the last two frames, to check conditions against
+#[derive(Default)]
+struct Frames {
+    old: BTreeMap<String, Option<f64>>,
+    current: BTreeMap<String, Option<f64>>,
+}
+
+impl Frames {
+    fn push(&mut self, values: BTreeMap<String, Option<f64>>) {
+        self.old = std::mem::replace(&mut self.current, values);
+    }
+
+    // All conditions have to pass, and an empty list never does
+    fn check(&self, conditions: &[Condition]) -> bool {
+        !conditions.is_empty()
+            && conditions.iter().all(|condition| {
+                let old = self.old.get(&condition.value).copied().flatten();
+                let current = self.current.get(&condition.value).copied().flatten();
+                condition.when.check(old, current)
+            })
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::{dry_run, load};
+
+#[cfg(not(target_os = "linux"))]
+pub fn load(_config: &MemoryConfig) -> anyhow::Result<Box<dyn super::Autosplitter>> {
+    Err(anyhow!("Memory autosplitters only work on Linux"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn dry_run(_config: &MemoryConfig) -> anyhow::Result<()> {
+    Err(anyhow!("Memory autosplitters only work on Linux"))
+}
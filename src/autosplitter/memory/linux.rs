@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::process::Process;
+use super::{Frames, MemoryConfig};
+use crate::autosplitter::{Autosplitter, AutosplitterCommand, TICK_INTERVAL};
+use crate::timer_state::{TimerMode, TimerState};
+
+// Looking through every process is slow, so it isn't done every frame
+static SEARCH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn load(config: &MemoryConfig) -> anyhow::Result<Box<dyn Autosplitter>> {
+    Ok(Box::new(Memory::new(config.clone())?))
+}
+
+/// Reads values out of a running game's memory and checks the conditions on them every frame.
+struct Memory {
+    config: MemoryConfig,
+    process: Option<Process>,
+    next_search: Instant,
+    frames: Frames,
+    loading: bool,
+    // A split that happened during a load, applied once the timer is running again
+    pending_split: bool,
+}
+
+impl Memory {
+    fn new(config: MemoryConfig) -> anyhow::Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            process: None,
+            next_search: Instant::now(),
+            frames: Frames::default(),
+            loading: false,
+            pending_split: false,
+        })
+    }
+
+    // Finds the game if it isn't attached yet, and drops it once it has exited. Returns
+    // whether the game is attached.
+    fn attach(&mut self) -> bool {
+        if self
+            .process
+            .as_ref()
+            .is_some_and(|process| !process.is_alive())
+        {
+            self.process = None;
+            self.frames = Frames::default();
+        }
+
+        let now = Instant::now();
+        if self.process.is_none() && now >= self.next_search {
+            self.next_search = now + SEARCH_INTERVAL;
+            self.process = Process::find(&self.config.process, self.config.pointer_size);
+        }
+        self.process.is_some()
+    }
+
+    fn read_values(&mut self) -> BTreeMap<String, Option<f64>> {
+        let process = self.process.as_mut().unwrap();
+        self.config
+            .values
+            .iter()
+            .map(|(name, path)| (name.clone(), process.read_value(path)))
+            .collect()
+    }
+
+    // Checks the conditions that make sense for where the run is, as LiveSplit does
+    fn tick(&mut self, running: bool) -> Vec<AutosplitterCommand> {
+        if !self.attach() {
+            return Vec::new();
+        }
+        let values = self.read_values();
+        self.frames.push(values);
+
+        let mut commands = Vec::new();
+        if !running {
+            if self.frames.check(&self.config.start) {
+                commands.push(AutosplitterCommand::Start);
+            }
+        } else if self.frames.check(&self.config.reset) {
+            commands.push(AutosplitterCommand::Reset);
+        } else if self.frames.check(&self.config.split) {
+            if self.loading {
+                self.pending_split = true;
+            } else {
+                commands.push(AutosplitterCommand::Split);
+            }
+        }
+
+        let loading = self.frames.check(&self.config.loading);
+        if loading != self.loading {
+            self.loading = loading;
+            if loading {
+                commands.push(AutosplitterCommand::PauseGameTime);
+            } else {
+                commands.push(AutosplitterCommand::ResumeGameTime);
+                if std::mem::take(&mut self.pending_split) {
+                    commands.push(AutosplitterCommand::Split);
+                }
+            }
+        }
+        commands
+    }
+}
+
+impl Autosplitter for Memory {
+    fn update(&mut self, timer: &TimerState) -> anyhow::Result<Vec<AutosplitterCommand>> {
+        let running = !matches!(timer.mode, TimerMode::Initial);
+        if !running {
+            self.pending_split = false;
+        }
+        Ok(self.tick(running))
+    }
+}
+
+/// Follows the game without running the timer, printing values as they change and what the
+/// timer would do. Runs until interrupted.
+pub fn dry_run(config: &MemoryConfig) -> anyhow::Result<()> {
+    let mut memory = Memory::new(config.clone())?;
+    println!(
+        "Waiting for process {}, press Ctrl-C to stop",
+        config.process
+    );
+
+    let mut attached = None;
+    let mut running = false;
+    loop {
+        let commands = memory.tick(running);
+
+        let pid = memory.process.as_ref().map(|process| process.pid());
+        if pid != attached {
+            match pid {
+                Some(pid) => println!("Attached to process {}", pid),
+                None => println!("Process exited"),
+            }
+            attached = pid;
+        }
+
+        let format = |value: Option<f64>| match value {
+            Some(value) => value.to_string(),
+            None => "unreadable".to_string(),
+        };
+        for (name, &current) in &memory.frames.current {
+            match memory.frames.old.get(name) {
+                None => println!("{}: {}", name, format(current)),
+                Some(&old) if old != current => {
+                    println!("{}: {} -> {}", name, format(old), format(current))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for command in commands {
+            println!("{:?}", command);
+            match command {
+                AutosplitterCommand::Start => running = true,
+                AutosplitterCommand::Reset => running = false,
+                _ => {}
+            }
+        }
+        thread::sleep(TICK_INTERVAL);
+    }
+}
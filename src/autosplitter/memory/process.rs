@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use super::PointerPath;
+
+/// A running process whose memory can be read, through `/proc/<pid>/mem`. This needs the same
+/// permission as attaching a debugger to it.
+pub struct Process {
+    pid: u32,
+    mem: File,
+    exe: PathBuf,
+    pointer_size: u8,
+    // Where each module starts, found the first time it's needed
+    modules: HashMap<Option<String>, u64>,
+}
+
+impl Process {
+    /// Finds a process by its name or the file name of its executable.
+    pub fn find(name: &str, pointer_size: u8) -> Option<Self> {
+        let own_pid = std::process::id();
+        fs::read_dir("/proc")
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|&pid| pid != own_pid)
+            .find(|&pid| {
+                let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default();
+                let exe = fs::read_link(format!("/proc/{}/exe", pid)).ok();
+                comm.trim_end() == name
+                    || exe.is_some_and(|exe| exe.file_name().is_some_and(|file| file == name))
+            })
+            .and_then(|pid| Self::open(pid, pointer_size))
+    }
+
+    fn open(pid: u32, pointer_size: u8) -> Option<Self> {
+        Some(Self {
+            pid,
+            mem: File::open(format!("/proc/{}/mem", pid)).ok()?,
+            exe: fs::read_link(format!("/proc/{}/exe", pid)).ok()?,
+            pointer_size,
+            modules: HashMap::new(),
+        })
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn is_alive(&self) -> bool {
+        Path::new(&format!("/proc/{}", self.pid)).exists()
+    }
+
+    /// Follows a pointer path, or returns `None` if any address along it can't be read.
+    pub fn read_value(&mut self, path: &PointerPath) -> Option<f64> {
+        let (first, rest) = path.offsets.split_first()?;
+        let mut address = self
+            .module_base(&path.module)?
+            .checked_add_signed(first.value().ok()?)?;
+        for offset in rest {
+            address = self
+                .read_pointer(address)?
+                .checked_add_signed(offset.value().ok()?)?;
+        }
+
+        let mut buf = vec![0; path.value_type.size()];
+        self.read(address, &mut buf)?;
+        Some(path.value_type.decode(&buf))
+    }
+
+    fn read_pointer(&self, address: u64) -> Option<u64> {
+        let mut buf = [0; 8];
+        self.read(address, &mut buf[..self.pointer_size as usize])?;
+        Some(u64::from_le_bytes(buf))
+    }
+
+    fn read(&self, address: u64, buf: &mut [u8]) -> Option<()> {
+        // A null pointer along the way usually means the game hasn't set it up yet
+        if address == 0 {
+            return None;
+        }
+        self.mem.read_exact_at(buf, address).ok()
+    }
+
+    fn module_base(&mut self, module: &Option<String>) -> Option<u64> {
+        if let Some(&base) = self.modules.get(module) {
+            return Some(base);
+        }
+        // Libraries can be loaded at any time, so a missing module is looked for again later
+        let base = self.find_module(module.as_deref())?;
+        self.modules.insert(module.clone(), base);
+        Some(base)
+    }
+
+    // The lowest address a module is mapped at, from lines of /proc/<pid>/maps like
+    // `7f12a000-7f12b000 r--p 00000000 08:01 1234 /usr/lib/libfoo.so`
+    fn find_module(&self, module: Option<&str>) -> Option<u64> {
+        let maps = fs::read_to_string(format!("/proc/{}/maps", self.pid)).ok()?;
+        maps.lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(6, ' ');
+                let range = fields.next()?;
+                let path = Path::new(fields.nth(4)?.trim_start());
+                let matches = match module {
+                    Some(module) => path.file_name().is_some_and(|file| file == module),
+                    None => path == self.exe,
+                };
+                let start = range.split_once('-')?.0;
+                matches
+                    .then(|| u64::from_str_radix(start, 16).ok())
+                    .flatten()
+            })
+            .min()
+    }
+}
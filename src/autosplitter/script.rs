@@ -0,0 +1,172 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use super::sources::LineSource;
+use super::{Autosplitter, AutosplitterCommand};
+use crate::timer_state::{TimerMode, TimerState};
+
+// Enough for any reasonable tick, while stopping an endless loop from freezing the timer
+static MAX_OPERATIONS: u64 = 1_000_000;
+
+// What the script can see of the timer during a tick
+#[derive(Default)]
+struct TimerInfo {
+    phase: &'static str,
+    current_split: i64,
+}
+
+/// An autosplitter written in Rhai. The script defines `tick()`, called every tick, and
+/// optionally `init()`, called once when it's loaded. Both can keep values in `this`.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    this: Dynamic,
+    commands: Rc<RefCell<Vec<AutosplitterCommand>>>,
+    timer_info: Rc<RefCell<TimerInfo>>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        // Printing would draw over the timer
+        engine.on_print(|_| {});
+        engine.on_debug(|_, _, _| {});
+
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let timer_info = Rc::new(RefCell::new(TimerInfo::default()));
+        let dir = path.parent().unwrap_or(Path::new("")).to_owned();
+        register_api(&mut engine, &commands, &timer_info, dir);
+
+        let ast = engine
+            .compile_file(path.to_owned())
+            .map_err(|err| anyhow!("Failed to load autosplitter script: {}", err))?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "tick" && f.params.is_empty())
+        {
+            return Err(anyhow!("Autosplitter script must define fn tick()"));
+        }
+
+        let mut script = Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            this: Dynamic::from_map(Default::default()),
+            commands,
+            timer_info,
+        };
+
+        // Top level statements run once, before init
+        script
+            .engine
+            .run_ast_with_scope(&mut script.scope, &script.ast)
+            .map_err(|err| anyhow!("Autosplitter script failed: {}", err))?;
+        if script
+            .ast
+            .iter_functions()
+            .any(|f| f.name == "init" && f.params.is_empty())
+        {
+            script.call("init")?;
+        }
+        Ok(script)
+    }
+
+    fn call(&mut self, name: &str) -> anyhow::Result<()> {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, ())
+            .map(|_| ())
+            .map_err(|err| anyhow!("Autosplitter script failed in {}(): {}", name, err))
+    }
+}
+
+impl Autosplitter for Script {
+    fn update(&mut self, timer: &TimerState) -> anyhow::Result<Vec<AutosplitterCommand>> {
+        *self.timer_info.borrow_mut() = TimerInfo {
+            phase: match timer.mode {
+                TimerMode::Initial => "Initial",
+                TimerMode::Running { .. } => "Running",
+                TimerMode::Paused { .. } => "Paused",
+                TimerMode::Finished { .. } => "Finished",
+            },
+            current_split: timer.splits.len() as i64,
+        };
+
+        self.call("tick")?;
+        Ok(self.commands.take())
+    }
+}
+
+// Rhai values have to be cloneable, so sources are shared
+#[derive(Clone)]
+struct ScriptSource(Rc<LineSource>);
+
+fn register_api(
+    engine: &mut Engine,
+    commands: &Rc<RefCell<Vec<AutosplitterCommand>>>,
+    timer_info: &Rc<RefCell<TimerInfo>>,
+    dir: PathBuf,
+) {
+    let command_fns = [
+        ("start", AutosplitterCommand::Start),
+        ("split", AutosplitterCommand::Split),
+        ("reset", AutosplitterCommand::Reset),
+        ("pause_game_time", AutosplitterCommand::PauseGameTime),
+        ("resume_game_time", AutosplitterCommand::ResumeGameTime),
+    ];
+    for (name, command) in command_fns {
+        let commands = commands.clone();
+        engine.register_fn(name, move || commands.borrow_mut().push(command));
+    }
+
+    let info = timer_info.clone();
+    engine.register_fn("timer_phase", move || info.borrow().phase.to_string());
+    let info = timer_info.clone();
+    engine.register_fn("current_split", move || info.borrow().current_split);
+
+    // Relative paths are relative to the script
+    let resolve = move |path: &str| dir.join(path);
+
+    engine.register_type_with_name::<ScriptSource>("LineSource");
+    engine.register_fn("lines", |source: &mut ScriptSource| -> Array {
+        source
+            .0
+            .new_lines()
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    });
+
+    let resolve_tail = resolve.clone();
+    engine.register_fn("tail_file", move |path: &str| {
+        ScriptSource(Rc::new(LineSource::tail_file(&resolve_tail(path), false)))
+    });
+    let resolve_pipe = resolve.clone();
+    engine.register_fn("read_pipe", move |path: &str| {
+        ScriptSource(Rc::new(LineSource::read_pipe(&resolve_pipe(path))))
+    });
+    engine.register_fn(
+        "run_process",
+        |command: &str| -> Result<ScriptSource, Box<EvalAltResult>> {
+            let source = LineSource::run_process(command)
+                .map_err(|err| format!("Failed to run {}: {}", command, err))?;
+            Ok(ScriptSource(Rc::new(source)))
+        },
+    );
+    engine.register_fn(
+        "read_file",
+        move |path: &str| -> Result<String, Box<EvalAltResult>> {
+            let path = resolve(path);
+            std::fs::read_to_string(&path)
+                .map_err(|err| format!("Failed to read {}: {}", path.display(), err).into())
+        },
+    );
+}
@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+static POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Lines of text read on another thread, so reading never holds up the timer. The thread stops
+/// once the source is dropped.
+pub struct LineSource {
+    lines: Receiver<String>,
+    stop: Arc<AtomicBool>,
+    process: Option<Child>,
+}
+
+impl LineSource {
+    fn spawn(read: impl FnOnce(Sender<String>, Arc<AtomicBool>) + Send + 'static) -> Self {
+        let (sender, lines) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        thread::spawn(move || read(sender, thread_stop));
+        Self {
+            lines,
+            stop,
+            process: None,
+        }
+    }
+
+    /// Follows a file as it's written to, like `tail -f`. Unless `from_start` is set, only lines
+    /// written after the source is created are read. The file doesn't have to exist yet, and may
+    /// be truncated or replaced.
+    pub fn tail_file(path: &Path, from_start: bool) -> Self {
+        let path = path.to_owned();
+        Self::spawn(move |sender, stop| tail_file(&path, from_start, &sender, &stop))
+    }
+
+    /// Reads lines from a named pipe, reopening it whenever the writer closes it.
+    pub fn read_pipe(path: &Path) -> Self {
+        let path = path.to_owned();
+        Self::spawn(move |sender, stop| {
+            while !stop.load(Ordering::Relaxed) {
+                // Opening blocks until there's a writer
+                let Ok(file) = File::open(&path) else {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                };
+                if !send_lines(BufReader::new(file), &sender) {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Runs a shell command, reading lines from its output. The command is killed when the
+    /// source is dropped.
+    pub fn run_process(command: &str) -> anyhow::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().unwrap();
+
+        let mut source = Self::spawn(move |sender, _| {
+            send_lines(BufReader::new(stdout), &sender);
+        });
+        source.process = Some(child);
+        Ok(source)
+    }
+
+    /// Lines read since the last call.
+    pub fn new_lines(&self) -> Vec<String> {
+        self.lines.try_iter().collect()
+    }
+}
+
+impl Drop for LineSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(process) = &mut self.process {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+fn tail_file(path: &PathBuf, from_start: bool, sender: &Sender<String>, stop: &AtomicBool) {
+    let mut reader: Option<BufReader<File>> = None;
+    let mut pos = 0;
+    // Whether to skip the old contents of a file that exists when tailing starts
+    let mut at_start = !from_start;
+    let mut partial = Vec::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        let len = std::fs::metadata(path).map(|meta| meta.len()).ok();
+        // Start over if the file was removed or truncated
+        if len.is_none_or(|len| len < pos) {
+            reader = None;
+            pos = 0;
+            partial.clear();
+        }
+
+        if reader.is_none()
+            && let Ok(mut file) = File::open(path)
+        {
+            if at_start {
+                pos = file.seek(SeekFrom::End(0)).unwrap_or(0);
+            }
+            reader = Some(BufReader::new(file));
+        }
+        at_start = false;
+
+        if let Some(reader) = &mut reader {
+            loop {
+                match reader.read_until(b'\n', &mut partial) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => pos += read as u64,
+                }
+                // A line without a newline is still being written
+                if partial.ends_with(b"\n") {
+                    if sender.send(decode_line(&partial)).is_err() {
+                        return;
+                    }
+                    partial.clear();
+                }
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// Sends each line until the end of input. Returns false if nobody is listening anymore.
+fn send_lines(reader: impl BufRead, sender: &Sender<String>) -> bool {
+    for line in reader.split(b'\n') {
+        let Ok(line) = line else { break };
+        if sender.send(decode_line(&line)).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+// Game logs aren't always valid UTF-8, so anything else is replaced rather than rejected
+fn decode_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .trim_end_matches(['\r', '\n'])
+        .to_string()
+}
@@ -0,0 +1,311 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+
+use crate::autosplitter;
+use crate::livesplit;
+use crate::split_file::{
+    create_split_file, read_split_file, read_split_file_unchecked, write_split_file, Fix, SplitFile,
+};
+use crate::stats;
+use crate::utils::{format_duration, Prefix, Sign};
+
+pub fn print_stats(path: &Path, json: bool) -> anyhow::Result<()> {
+    let split_file = read_split_file(path).context("Failed to read splits file")?;
+    let run_stats = stats::get_run_stats(&split_file);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&run_stats)?);
+    } else {
+        print!("{}", stats::format_run_stats(&run_stats));
+    }
+    Ok(())
+}
+
+/// Creates a splits file, prompting for anything not given.
+pub fn new_split_file(
+    path: &Path,
+    title: Option<String>,
+    category: Option<String>,
+    names_path: Option<&Path>,
+    mut split_names: Vec<String>,
+) -> anyhow::Result<()> {
+    // Check up front so the user isn't prompted for nothing
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+
+    let title = match title {
+        Some(title) => title,
+        None => prompt_line("Title: ")?,
+    };
+    let category = match category {
+        Some(category) => category,
+        None => prompt_line("Category: ")?,
+    };
+
+    if let Some(names_path) = names_path {
+        let text = std::fs::read_to_string(names_path)
+            .with_context(|| format!("Failed to read {}", names_path.display()))?;
+        split_names.extend(read_split_names(&text));
+    }
+    if split_names.is_empty() {
+        println!("Split names, one per line (empty line to finish):");
+        loop {
+            let name = prompt_line(&format!("{:>3}. ", split_names.len() + 1))?;
+            if name.is_empty() {
+                break;
+            }
+            split_names.push(name);
+        }
+    }
+
+    if title.is_empty() || category.is_empty() {
+        return Err(anyhow!("Title and category cannot be empty"));
+    }
+    if split_names.is_empty() {
+        return Err(anyhow!("Split names cannot be empty"));
+    }
+
+    let split_file = SplitFile::new(path, title, category, split_names);
+    create_split_file(&split_file)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    println!(
+        "Created {} with {} splits",
+        path.display(),
+        split_file.split_names.len()
+    );
+    Ok(())
+}
+
+// One split name per line, ignoring blank lines
+fn read_split_names(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+}
+
+fn prompt_line(prompt: &str) -> anyhow::Result<String> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+
+    // End of input reads as an empty line
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Reports every problem in a splits file, and with `fix`, offers to repair each one.
+pub fn check_split_file(path: &Path, fix: bool) -> anyhow::Result<()> {
+    let mut split_file = read_split_file_unchecked(path).context("Failed to read splits file")?;
+
+    let problems = split_file.problems();
+    for problem in &problems {
+        println!("{}: {}", problem.location, problem.message);
+    }
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    if !fix {
+        return Err(anyhow!("Found {} problem(s)", problems.len()));
+    }
+
+    // Fixes can resolve other problems too, so look again after each one
+    let mut declined: Vec<Fix> = Vec::new();
+    let mut applied = 0;
+    while let Some((problem, fix)) = split_file
+        .problems()
+        .into_iter()
+        .filter_map(|problem| problem.fix.map(|fix| (problem, fix)))
+        .find(|(_, fix)| !declined.contains(fix))
+    {
+        let answer = prompt_line(&format!(
+            "{}: {}\n  {}? [y/N] ",
+            problem.location,
+            problem.message,
+            fix.description()
+        ))?;
+        if answer.eq_ignore_ascii_case("y") {
+            split_file.apply_fix(fix);
+            applied += 1;
+        } else {
+            declined.push(fix);
+        }
+    }
+
+    if applied > 0 {
+        write_split_file(&split_file).context("Failed to write splits file")?;
+        println!("Applied {} fix(es)", applied);
+    }
+
+    let remaining = split_file.problems().len();
+    if remaining > 0 {
+        return Err(anyhow!("{} problem(s) remain", remaining));
+    }
+    Ok(())
+}
+
+/// Rebuilds golds from the PB and history, reporting each gold that changed.
+pub fn recompute_golds(path: &Path, dry_run: bool) -> anyhow::Result<()> {
+    let mut split_file = read_split_file(path).context("Failed to read splits file")?;
+
+    let result = split_file.recompute_golds();
+
+    let fmt_dur = |dur: Option<Duration>| match dur {
+        Some(dur) => format_duration(dur, 3, Sign::Positive, Prefix::NoneOrMinus),
+        None => "-".to_string(),
+    };
+    for change in &result.changes {
+        println!(
+            "Gold {} ({}): {} -> {}",
+            change.idx + 1,
+            split_file.split_names[change.idx],
+            fmt_dur(change.old),
+            fmt_dur(change.new)
+        );
+    }
+    for &idx in &result.unrecorded {
+        println!(
+            "Gold {} ({}): {} has no recorded time, keeping it",
+            idx + 1,
+            split_file.split_names[idx],
+            fmt_dur(split_file.golds[idx].as_ref().map(|gold| gold.duration)),
+        );
+    }
+
+    if result.changes.is_empty() {
+        println!("Golds already match the recorded times");
+    } else if dry_run {
+        println!("{} gold(s) would change", result.changes.len());
+    } else {
+        split_file
+            .validate()
+            .context("Recomputed golds are inconsistent with the PB")?;
+        write_split_file(&split_file).context("Failed to write splits file")?;
+        println!("Updated {} gold(s)", result.changes.len());
+    }
+    Ok(())
+}
+
+pub enum SplitEdit {
+    Insert {
+        position: usize,
+        name: String,
+        drop_pb: bool,
+    },
+    Remove {
+        position: usize,
+    },
+    Merge {
+        position: usize,
+        name: Option<String>,
+    },
+    Rename {
+        position: usize,
+        name: String,
+    },
+}
+
+/// Changes the splits of a file, keeping golds, PB and history in line. Positions start at 1.
+pub fn edit_splits(path: &Path, edit: SplitEdit) -> anyhow::Result<()> {
+    let mut split_file = read_split_file(path).context("Failed to read splits file")?;
+    let split_count = split_file.split_names.len();
+
+    let check_position = |position: usize, max: usize| {
+        if position == 0 || position > max {
+            Err(anyhow!("Position must be between 1 and {}", max))
+        } else {
+            Ok(position - 1)
+        }
+    };
+
+    match edit {
+        SplitEdit::Insert {
+            position,
+            name,
+            drop_pb,
+        } => {
+            let idx = check_position(position, split_count + 1)?;
+            // There's no PB time for a new last split, and the PB can't end without one
+            let ends_pb = idx == split_count && split_file.personal_best.is_some();
+            if ends_pb && !drop_pb {
+                return Err(anyhow!(
+                    "Inserting a last split removes the personal best, which has no time for it (pass --drop-pb to allow this)"
+                ));
+            }
+            split_file.insert_split(idx, name);
+            if idx < split_count {
+                println!(
+                    "Cleared the gold and segment history of {}",
+                    split_file.split_names[idx + 1]
+                );
+            }
+            if ends_pb {
+                split_file.personal_best = None;
+                println!("Removed the personal best");
+            }
+        }
+        SplitEdit::Remove { position } => {
+            let idx = check_position(position, split_count)?;
+            split_file.remove_split(idx)?;
+        }
+        SplitEdit::Merge { position, name } => {
+            if split_count < 2 {
+                return Err(anyhow!("Merging needs at least two splits"));
+            }
+            let idx = check_position(position, split_count - 1)?;
+            let name = name.unwrap_or_else(|| split_file.split_names[idx + 1].clone());
+            split_file.merge_segments(idx, name);
+        }
+        SplitEdit::Rename { position, name } => {
+            let idx = check_position(position, split_count)?;
+            split_file.rename_split(idx, name);
+        }
+    }
+
+    split_file
+        .validate()
+        .context("Edited splits file is invalid")?;
+    write_split_file(&split_file).context("Failed to write splits file")?;
+
+    for (i, name) in split_file.split_names.iter().enumerate() {
+        println!("{:>3}. {}", i + 1, name);
+    }
+    Ok(())
+}
+
+/// Converts a LiveSplit `.lss` file into a new splits file.
+pub fn import_lss(lss_path: &Path, path: &Path) -> anyhow::Result<()> {
+    let lss = std::fs::read_to_string(lss_path)
+        .with_context(|| format!("Failed to read {}", lss_path.display()))?;
+    let split_file = livesplit::import_lss(&lss, path)?;
+    split_file
+        .validate()
+        .context("Imported splits file is invalid")?;
+    create_split_file(&split_file)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    println!(
+        "Imported {} with {} splits and {} attempts",
+        path.display(),
+        split_file.split_names.len(),
+        split_file.attempts
+    );
+    Ok(())
+}
+
+/// Writes a splits file as a LiveSplit `.lss` file.
+pub fn export_lss(path: &Path, lss_path: &Path) -> anyhow::Result<()> {
+    let split_file = read_split_file(path).context("Failed to read splits file")?;
+    std::fs::write(lss_path, livesplit::export_lss(&split_file))
+        .with_context(|| format!("Failed to write {}", lss_path.display()))?;
+    println!("Exported {}", lss_path.display());
+    Ok(())
+}
+
+pub fn dry_run_autosplitter(path: &Path, from_start: bool) -> anyhow::Result<()> {
+    let split_file = read_split_file(path).context("Failed to read splits file")?;
+    autosplitter::dry_run(&split_file, from_start)
+}
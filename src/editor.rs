@@ -0,0 +1,437 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Attribute;
+
+use crate::rotty::{Block, Image, Renderer, TextAlign};
+use crate::settings::Theme;
+use crate::split_file::{
+    get_segment, read_split_file, write_split_file, Gold, PersonalBest, Split, SplitFile,
+};
+use crate::utils::{format_duration, parse_color, parse_duration, Prefix, Sign};
+
+static NAME_WIDTH: u16 = 20;
+static COL_WIDTH: u16 = 12;
+static EDITOR_WIDTH: u16 = NAME_WIDTH + COL_WIDTH * 3;
+
+// Rows above the splits hold the title and category
+static HEADER_ROWS: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Name,
+    Gold,
+    PbSplit,
+}
+
+impl Column {
+    fn left(self) -> Self {
+        match self {
+            Column::Name | Column::Gold => Column::Name,
+            Column::PbSplit => Column::Gold,
+        }
+    }
+
+    fn right(self) -> Self {
+        match self {
+            Column::Name => Column::Gold,
+            Column::Gold | Column::PbSplit => Column::PbSplit,
+        }
+    }
+}
+
+/// Interactive editor for the names, golds and PB of a splits file.
+pub struct Editor {
+    renderer: Renderer,
+    theme: &'static Theme,
+    split_file: SplitFile,
+
+    row: usize,
+    col: Column,
+    // Text of the field being edited, if any
+    input: Option<String>,
+
+    status: String,
+    status_is_error: bool,
+    unsaved: bool,
+    confirm_quit: bool,
+}
+
+impl Editor {
+    pub fn new(splits_file: &Path, theme: &'static Theme) -> anyhow::Result<Self> {
+        let split_file = read_split_file(splits_file).context("Failed to read splits file")?;
+
+        Ok(Self {
+            renderer: Renderer::new(),
+            theme,
+            split_file,
+            row: HEADER_ROWS,
+            col: Column::Name,
+            input: None,
+            status: String::new(),
+            status_is_error: false,
+            unsaved: false,
+            confirm_quit: false,
+        })
+    }
+
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        self.renderer.set_default_colors(
+            parse_color(self.theme.normal_text),
+            parse_color(self.theme.bg),
+        );
+
+        loop {
+            let block = self.render_view();
+            self.renderer.render(&block)?;
+
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key)?
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    fn split_idx(&self) -> Option<usize> {
+        self.row.checked_sub(HEADER_ROWS)
+    }
+
+    fn set_status(&mut self, status: &str, is_error: bool) {
+        self.status = status.to_string();
+        self.status_is_error = is_error;
+    }
+
+    // Returns false when the editor should exit
+    fn handle_key(&mut self, key: KeyEvent) -> anyhow::Result<bool> {
+        if self.input.is_some() {
+            self.handle_input_key(key);
+            return Ok(true);
+        }
+
+        let quitting = self.confirm_quit;
+        self.confirm_quit = false;
+        let split_count = self.split_file.split_names.len();
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if !self.unsaved || quitting {
+                    return Ok(false);
+                }
+                self.confirm_quit = true;
+                self.set_status("Unsaved changes, press q again to quit", true);
+            }
+            KeyCode::Up if shift => self.move_split_up(),
+            KeyCode::Down if shift => self.move_split_down(),
+            KeyCode::Char('K') => self.move_split_up(),
+            KeyCode::Char('J') => self.move_split_down(),
+            KeyCode::Up | KeyCode::Char('k') => self.row = self.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.row = (self.row + 1).min(HEADER_ROWS + split_count - 1)
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.col = self.col.left(),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => self.col = self.col.right(),
+            KeyCode::Enter | KeyCode::Char('e') => self.input = Some(self.field_text()),
+            KeyCode::Char('i') => self.insert_split(0),
+            KeyCode::Char('a') | KeyCode::Char('o') => self.insert_split(1),
+            KeyCode::Char('d') => {
+                if let Some(idx) = self.split_idx() {
+                    match self.split_file.remove_split(idx) {
+                        Ok(()) => {
+                            self.changed();
+                            let split_count = self.split_file.split_names.len();
+                            self.row = self.row.min(HEADER_ROWS + split_count - 1);
+                        }
+                        Err(e) => self.set_status(&e.to_string(), true),
+                    }
+                }
+            }
+            KeyCode::Char('s') => self.save()?,
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) {
+        let input = self.input.as_mut().unwrap();
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let text = self.input.take().unwrap();
+                if let Err(e) = self.commit_field(text.trim()) {
+                    self.set_status(&e, true);
+                } else {
+                    self.changed();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn field_text(&self) -> String {
+        let fmt_dur = |dur: Option<Duration>| {
+            dur.map(|d| format_duration(d, 3, Sign::Positive, Prefix::NoneOrMinus))
+                .unwrap_or_default()
+        };
+
+        match (self.split_idx(), self.row) {
+            (None, 0) => self.split_file.title.clone(),
+            (None, _) => self.split_file.category.clone(),
+            (Some(idx), _) => match self.col {
+                Column::Name => self.split_file.split_names[idx].clone(),
+                Column::Gold => fmt_dur(self.gold(idx)),
+                Column::PbSplit => fmt_dur(self.pb_split(idx)),
+            },
+        }
+    }
+
+    fn commit_field(&mut self, text: &str) -> Result<(), String> {
+        let idx = match (self.split_idx(), self.row) {
+            (Some(idx), _) => idx,
+            (None, row) => {
+                if text.is_empty() {
+                    return Err("Title and category cannot be empty".to_string());
+                }
+                if row == 0 {
+                    self.split_file.title = text.to_string();
+                } else {
+                    self.split_file.category = text.to_string();
+                }
+                return Ok(());
+            }
+        };
+
+        // An empty time or "-" clears it
+        let parse_time = || {
+            if text.is_empty() || text == "-" {
+                Ok(None)
+            } else {
+                match parse_duration(text) {
+                    Some(time) => Ok(Some(time)),
+                    None => Err("Invalid time, expected D:H:MM:SS.mmm".to_string()),
+                }
+            }
+        };
+
+        match self.col {
+            Column::Name => {
+                if text.is_empty() {
+                    return Err("Split names cannot be empty".to_string());
+                }
+                self.split_file.rename_split(idx, text.to_string());
+            }
+            Column::Gold => {
+                let time = parse_time()?;
+                self.split_file.golds[idx] = time.map(|duration| Gold { duration });
+            }
+            Column::PbSplit => {
+                let time = parse_time()?;
+                let split_count = self.split_file.split_names.len();
+                let attempt = self.split_file.attempts;
+                let pb = self
+                    .split_file
+                    .personal_best
+                    .get_or_insert_with(|| PersonalBest {
+                        attempt,
+                        splits: vec![None; split_count],
+                    });
+                pb.splits[idx] = time.map(|time| Split { time });
+                if pb.splits.iter().all(Option::is_none) {
+                    self.split_file.personal_best = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Inserts a split before (offset 0) or after (offset 1) the current one and starts naming it
+    fn insert_split(&mut self, offset: usize) {
+        let idx = match self.split_idx() {
+            Some(idx) => idx + offset,
+            None => 0,
+        };
+        self.split_file.insert_split(idx, "New split".to_string());
+        self.changed();
+        self.row = HEADER_ROWS + idx;
+        self.col = Column::Name;
+        self.input = Some(String::new());
+    }
+
+    fn move_split_up(&mut self) {
+        if let Some(idx) = self.split_idx()
+            && idx > 0
+        {
+            self.split_file.swap_segments(idx - 1);
+            self.row -= 1;
+            self.changed();
+        }
+    }
+
+    fn move_split_down(&mut self) {
+        if let Some(idx) = self.split_idx()
+            && idx + 1 < self.split_file.split_names.len()
+        {
+            self.split_file.swap_segments(idx);
+            self.row += 1;
+            self.changed();
+        }
+    }
+
+    // Marks the file as modified and reports whether it can be saved as is
+    fn changed(&mut self) {
+        self.unsaved = true;
+        match self.split_file.validate() {
+            Ok(()) => self.set_status("", false),
+            Err(e) => self.set_status(&e.to_string(), true),
+        }
+    }
+
+    fn save(&mut self) -> anyhow::Result<()> {
+        if let Err(e) = self.split_file.validate() {
+            self.set_status(&format!("Not saved: {}", e), true);
+            return Ok(());
+        }
+        write_split_file(&self.split_file).context("Failed to write splits file")?;
+        self.unsaved = false;
+        self.set_status("Saved", false);
+        Ok(())
+    }
+
+    fn gold(&self, idx: usize) -> Option<Duration> {
+        self.split_file.golds[idx].as_ref().map(|g| g.duration)
+    }
+
+    fn pb_split(&self, idx: usize) -> Option<Duration> {
+        let pb = self.split_file.personal_best.as_ref()?;
+        pb.splits[idx].as_ref().map(|s| s.time)
+    }
+
+    fn pb_segment(&self, idx: usize) -> Option<Duration> {
+        let pb = self.split_file.personal_best.as_ref()?;
+        get_segment(&pb.splits, idx)
+    }
+
+    fn render_view(&self) -> Block {
+        let theme = self.theme;
+        let label_color = parse_color(theme.label_text);
+
+        let title_row = self.render_cell(0, None, &self.split_file.title, EDITOR_WIDTH);
+        let category_row = self.render_cell(1, None, &self.split_file.category, EDITOR_WIDTH);
+
+        let headers = [
+            ("", NAME_WIDTH),
+            ("Gold", COL_WIDTH),
+            ("PB Split", COL_WIDTH),
+            ("PB Segment", COL_WIDTH),
+        ]
+        .map(|(h, width)| {
+            Image::new(h, width, TextAlign::Right)
+                .fg_color(label_color)
+                .build()
+        });
+        let line_sep = Image::new(
+            &"─".repeat(EDITOR_WIDTH as usize),
+            EDITOR_WIDTH,
+            TextAlign::Left,
+        )
+        .fg_color(label_color)
+        .build();
+
+        let fmt_dur = |dur: Option<Duration>| match dur {
+            Some(dur) => format_duration(dur, 3, Sign::Positive, Prefix::NoneOrMinus),
+            None => "-".to_string(),
+        };
+
+        let split_rows = (0..self.split_file.split_names.len()).map(|idx| {
+            let row = HEADER_ROWS + idx;
+            Block::hcat([
+                self.render_cell(
+                    row,
+                    Some(Column::Name),
+                    &self.split_file.split_names[idx],
+                    NAME_WIDTH,
+                ),
+                self.render_cell(row, Some(Column::Gold), &fmt_dur(self.gold(idx)), COL_WIDTH),
+                self.render_cell(
+                    row,
+                    Some(Column::PbSplit),
+                    &fmt_dur(self.pb_split(idx)),
+                    COL_WIDTH,
+                ),
+                Image::new(&fmt_dur(self.pb_segment(idx)), COL_WIDTH, TextAlign::Right)
+                    .fg_color(label_color)
+                    .build(),
+            ])
+        });
+
+        let status_color = if self.status_is_error {
+            parse_color(theme.behind_lose)
+        } else {
+            parse_color(theme.ahead_gain)
+        };
+        let status = Image::new(&self.status, EDITOR_WIDTH, TextAlign::Left)
+            .fg_color(status_color)
+            .build();
+
+        let help = [
+            "Enter: edit   i/a: insert before/after   d: remove",
+            "Shift+Up/Down or K/J: move split   s: save   q: quit",
+        ]
+        .map(|line| {
+            Image::new(line, EDITOR_WIDTH, TextAlign::Left)
+                .fg_color(label_color)
+                .build()
+        });
+
+        let mut sections = vec![
+            title_row,
+            category_row,
+            Block::hcat(headers),
+            line_sep.clone(),
+        ];
+        sections.extend(split_rows);
+        sections.push(line_sep);
+        sections.push(status);
+        sections.extend(help);
+        Block::vcat(sections)
+    }
+
+    // Renders a field, highlighting it if selected and showing the input text if being edited
+    fn render_cell(&self, row: usize, col: Option<Column>, text: &str, width: u16) -> Block {
+        let selected = self.row == row && (col.is_none() || col == Some(self.col));
+        let align = match col {
+            None => TextAlign::Center,
+            Some(Column::Name) => TextAlign::Left,
+            Some(_) => TextAlign::Right,
+        };
+
+        let image = match (&self.input, selected) {
+            (Some(input), true) => {
+                Image::new(&format!("{}_", input), width, align).attr(Attribute::Underlined)
+            }
+            _ => Image::new(text, width, align),
+        };
+        let image = if col.is_none() {
+            image.attr(Attribute::Bold)
+        } else {
+            image
+        };
+
+        let bg = Image::new(&" ".repeat(width as usize), width, TextAlign::Left);
+        let bg = if selected {
+            bg.bg_color(parse_color(self.theme.highlight))
+        } else {
+            bg
+        };
+        bg.build().stack(image.build())
+    }
+}
@@ -0,0 +1,260 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::timer_state::{TimerMode, TimerState};
+use crate::utils::{format_duration, get_run_summary, Prefix, SegSummary, Sign};
+
+/// How far ahead (negative) or behind a split is compared to the PB.
+#[derive(Clone, Copy, Debug)]
+pub struct Delta {
+    pub duration: Duration,
+    pub negative: bool,
+}
+
+impl Delta {
+    pub fn format(&self, ms_digits: u32) -> String {
+        format_duration(
+            self.duration,
+            ms_digits,
+            (!self.negative).into(),
+            Prefix::PlusOrMinus,
+        )
+    }
+}
+
+/// Something that happened to the run. Events are sent after the timer state has changed, except
+/// for `Reset`, which is sent while the reset run's splits are still there.
+#[derive(Clone, Debug)]
+pub enum TimerEvent {
+    RunStarted,
+    Split {
+        index: usize,
+        time: Duration,
+        delta: Option<Delta>,
+    },
+    GoldAchieved {
+        index: usize,
+        duration: Duration,
+    },
+    // The run fell behind the PB at this split after being level or ahead
+    PbPaceLost {
+        index: usize,
+    },
+    PbAchieved {
+        time: Duration,
+    },
+    Paused {
+        elapsed: Duration,
+    },
+    Resumed,
+    Reset {
+        saved: bool,
+    },
+    Finished {
+        time: Duration,
+    },
+}
+
+/// The kind of a timer event, as named in the settings file and event log.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EventKind {
+    RunStarted,
+    Split,
+    GoldAchieved,
+    PbPaceLost,
+    PbAchieved,
+    Paused,
+    Resumed,
+    Reset,
+    Finished,
+}
+
+impl TimerEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            TimerEvent::RunStarted => EventKind::RunStarted,
+            TimerEvent::Split { .. } => EventKind::Split,
+            TimerEvent::GoldAchieved { .. } => EventKind::GoldAchieved,
+            TimerEvent::PbPaceLost { .. } => EventKind::PbPaceLost,
+            TimerEvent::PbAchieved { .. } => EventKind::PbAchieved,
+            TimerEvent::Paused { .. } => EventKind::Paused,
+            TimerEvent::Resumed => EventKind::Resumed,
+            TimerEvent::Reset { .. } => EventKind::Reset,
+            TimerEvent::Finished { .. } => EventKind::Finished,
+        }
+    }
+
+    /// The split the event is about, if any.
+    pub fn split_index(&self) -> Option<usize> {
+        match self {
+            TimerEvent::Split { index, .. }
+            | TimerEvent::GoldAchieved { index, .. }
+            | TimerEvent::PbPaceLost { index } => Some(*index),
+            _ => None,
+        }
+    }
+}
+
+/// Events for the split that was just made, which may have finished the run.
+pub fn split_events(timer: &TimerState) -> Vec<TimerEvent> {
+    let index = timer.splits.len() - 1;
+    let time = timer.splits[index].unwrap();
+    let summary = get_run_summary(timer);
+    let seg = &summary[index];
+
+    let delta = seg.live_delta.map(|duration| Delta {
+        duration,
+        negative: seg.live_delta_neg,
+    });
+    let mut events = vec![TimerEvent::Split { index, time, delta }];
+    if seg.is_gold_new {
+        let duration = seg.live_seg.unwrap();
+        events.push(TimerEvent::GoldAchieved { index, duration });
+    }
+
+    // Compared to the last split with a delta, or the start of the run where it was level
+    let behind =
+        |seg: &SegSummary| seg.live_delta.is_some_and(|d| !d.is_zero()) && !seg.live_delta_neg;
+    let was_behind = summary[..index]
+        .iter()
+        .rev()
+        .find(|seg| seg.live_delta.is_some())
+        .is_some_and(behind);
+    if behind(seg) && !was_behind {
+        events.push(TimerEvent::PbPaceLost { index });
+    }
+
+    if let TimerMode::Finished { .. } = timer.mode {
+        events.push(TimerEvent::Finished { time });
+        // Practice runs aren't saved and segment practice isn't a whole run, so neither can be
+        // a PB
+        let full_run = !timer.practice && timer.segment_practice.is_none();
+        if full_run && delta.is_none_or(|delta| delta.negative) {
+            events.push(TimerEvent::PbAchieved { time });
+        }
+    }
+    events
+}
+
+pub fn format_time(time: Duration) -> String {
+    format_duration(time, 3, Sign::Positive, Prefix::NoneOrMinus)
+}
+
+pub trait EventListener {
+    fn on_event(&mut self, event: &TimerEvent, timer: &TimerState) -> anyhow::Result<()>;
+}
+
+/// A listener that records events in the splits file, which other listeners only read.
+pub trait EventRecorder {
+    fn on_event(&mut self, event: &TimerEvent, timer: &mut TimerState) -> anyhow::Result<()>;
+}
+
+/// Passes timer events on to everything subscribed, in the order they subscribed. Recorders hear
+/// each event first, and one failing stops the event there, so nothing hears about a run that
+/// wasn't kept. Listeners are side effects, so one failing doesn't stop the timer or the other
+/// listeners.
+#[derive(Default)]
+pub struct EventBus {
+    recorders: Vec<Box<dyn EventRecorder>>,
+    listeners: Vec<Subscriber>,
+}
+
+struct Subscriber {
+    listener: Box<dyn EventListener>,
+    // The first error the listener returned, kept to report later
+    error: Option<anyhow::Error>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, listener: impl EventListener + 'static) {
+        self.listeners.push(Subscriber {
+            listener: Box::new(listener),
+            error: None,
+        });
+    }
+
+    pub fn subscribe_recorder(&mut self, recorder: impl EventRecorder + 'static) {
+        self.recorders.push(Box::new(recorder));
+    }
+
+    pub fn emit(&mut self, event: &TimerEvent, timer: &mut TimerState) -> anyhow::Result<()> {
+        for recorder in self.recorders.iter_mut() {
+            recorder.on_event(event, timer)?;
+        }
+        for subscriber in self.listeners.iter_mut() {
+            if let Err(err) = subscriber.listener.on_event(event, timer) {
+                subscriber.error.get_or_insert(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// The first error from each listener that has failed since the last call.
+    pub fn take_errors(&mut self) -> Vec<anyhow::Error> {
+        self.listeners
+            .iter_mut()
+            .filter_map(|subscriber| subscriber.error.take())
+            .collect()
+    }
+}
+
+/// Appends each event to a file as a line of JSON, for other programs to follow along.
+pub struct EventLog {
+    file: File,
+}
+
+impl EventLog {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open event log {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl EventListener for EventLog {
+    fn on_event(&mut self, event: &TimerEvent, timer: &TimerState) -> anyhow::Result<()> {
+        let mut line = Map::new();
+        line.insert("event".to_string(), json!(event.kind()));
+        let mut insert = |key: &str, value: Value| line.insert(key.to_string(), value);
+
+        match event {
+            TimerEvent::Split { index, time, delta } => {
+                insert("split_index", json!(index));
+                insert("split_name", json!(timer.split_file.split_names[*index]));
+                insert("time", json!(format_time(*time)));
+                insert("delta", json!(delta.map(|delta| delta.format(3))));
+            }
+            TimerEvent::PbPaceLost { index } => {
+                insert("split_index", json!(index));
+                insert("split_name", json!(timer.split_file.split_names[*index]));
+            }
+            TimerEvent::GoldAchieved { index, duration } => {
+                insert("split_index", json!(index));
+                insert("split_name", json!(timer.split_file.split_names[*index]));
+                insert("duration", json!(format_time(*duration)));
+            }
+            TimerEvent::PbAchieved { time } | TimerEvent::Finished { time } => {
+                insert("time", json!(format_time(*time)));
+            }
+            TimerEvent::Paused { elapsed } => {
+                insert("time", json!(format_time(*elapsed)));
+            }
+            TimerEvent::Reset { saved } => {
+                insert("saved", json!(saved));
+            }
+            TimerEvent::RunStarted | TimerEvent::Resumed => {}
+        }
+
+        writeln!(self.file, "{}", Value::Object(line))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use anyhow::Context;
+
+use crate::events::{format_time, EventKind, EventListener, TimerEvent};
+use crate::timer_state::TimerState;
+use crate::utils::get_elapsed;
+
+/// Runs the shell command configured for each kind of event, without waiting for it to finish.
+pub struct Hooks {
+    commands: HashMap<EventKind, String>,
+}
+
+impl Hooks {
+    pub fn new(commands: HashMap<EventKind, String>) -> Self {
+        Self { commands }
+    }
+}
+
+impl EventListener for Hooks {
+    fn on_event(&mut self, event: &TimerEvent, timer: &TimerState) -> anyhow::Result<()> {
+        let Some(command) = self.commands.get(&event.kind()) else {
+            return Ok(());
+        };
+
+        let split_file = &timer.split_file;
+        let (time, delta) = match event {
+            TimerEvent::Split { time, delta, .. } => (*time, delta.map(|delta| delta.format(3))),
+            TimerEvent::GoldAchieved { duration, .. } => (*duration, None),
+            TimerEvent::PbAchieved { time } | TimerEvent::Finished { time } => (*time, None),
+            TimerEvent::Paused { elapsed } => (*elapsed, None),
+            _ => (get_elapsed(timer), None),
+        };
+        // Events that aren't about a split are about the segment being run
+        let split_index = event
+            .split_index()
+            .unwrap_or(timer.splits.len())
+            .min(split_file.split_names.len() - 1);
+
+        let mut child = Command::new("sh");
+        child
+            .arg("-c")
+            .arg(command)
+            .env("FLITTER_EVENT", format!("{:?}", event.kind()))
+            .env("FLITTER_TITLE", &split_file.title)
+            .env("FLITTER_CATEGORY", &split_file.category)
+            .env("FLITTER_SPLIT_INDEX", (split_index + 1).to_string())
+            .env("FLITTER_SPLIT_NAME", &split_file.split_names[split_index])
+            .env("FLITTER_TIME", format_time(time))
+            .env("FLITTER_DELTA", delta.unwrap_or_default())
+            // Output would draw over the timer
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let mut child = child
+            .spawn()
+            .with_context(|| format!("Failed to run hook {:?}: {}", event.kind(), command))?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
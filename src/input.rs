@@ -0,0 +1,56 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use device_query::{DeviceQuery, DeviceState, Keycode};
+
+use crate::settings::Action;
+
+// Short enough to time key presses well within a frame, without querying the keyboard more than
+// a few hundred times a second
+static POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+/// A hotkey action along with when its key press was detected.
+#[derive(Clone, Copy)]
+pub struct ActionEvent {
+    pub action: Action,
+    pub time: Instant,
+}
+
+/// Polls the global hotkeys on their own thread so key presses are timestamped as soon as
+/// they're seen, however long rendering takes. The thread stops once the receiver is dropped.
+pub fn spawn_hotkey_thread(hotkeys: HashMap<Keycode, Action>) -> Receiver<ActionEvent> {
+    spawn_shared_hotkey_thread(vec![hotkeys]).remove(0)
+}
+
+/// Like `spawn_hotkey_thread`, but polls several sets of hotkeys on one thread, giving one
+/// receiver for each set. The thread stops once any of the receivers is dropped.
+pub fn spawn_shared_hotkey_thread(
+    hotkey_sets: Vec<HashMap<Keycode, Action>>,
+) -> Vec<Receiver<ActionEvent>> {
+    let (senders, receivers): (Vec<_>, Vec<_>) =
+        hotkey_sets.iter().map(|_| mpsc::channel()).unzip();
+
+    thread::spawn(move || {
+        let device_state = DeviceState::new();
+        let mut prev_keys = HashSet::new();
+        loop {
+            let keys: HashSet<Keycode> = device_state.get_keys().into_iter().collect();
+            let time = Instant::now();
+            for key in keys.difference(&prev_keys) {
+                for (hotkeys, sender) in hotkey_sets.iter().zip(&senders) {
+                    if let Some(&action) = hotkeys.get(key)
+                        && sender.send(ActionEvent { action, time }).is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+            prev_keys = keys;
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+
+    receivers
+}
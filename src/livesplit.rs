@@ -0,0 +1,297 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use roxmltree::{Document, Node};
+
+use crate::split_file::{Attempt, Gold, PersonalBest, Split, SplitFile};
+
+/// Reads a LiveSplit `.lss` file into a splits file that will be saved at `path`.
+pub fn import_lss(lss: &str, path: &Path) -> anyhow::Result<SplitFile> {
+    let doc = Document::parse(lss).context("Invalid LiveSplit file")?;
+    let run = doc.root_element();
+    if !run.has_tag_name("Run") {
+        return Err(anyhow!("Invalid LiveSplit file: expected a <Run> element"));
+    }
+
+    let segments: Vec<Node> = child(run, "Segments")
+        .ok_or_else(|| anyhow!("Invalid LiveSplit file: missing <Segments>"))?
+        .children()
+        .filter(|node| node.has_tag_name("Segment"))
+        .collect();
+    if segments.is_empty() {
+        return Err(anyhow!("LiveSplit file has no segments"));
+    }
+    let split_names = segments
+        .iter()
+        .map(|segment| child_text(*segment, "Name").to_string())
+        .collect();
+
+    let mut split_file = SplitFile::new(
+        path,
+        child_text(run, "GameName").to_string(),
+        child_text(run, "CategoryName").to_string(),
+        split_names,
+    );
+
+    for (i, segment) in segments.iter().enumerate() {
+        split_file.golds[i] = child(*segment, "BestSegmentTime")
+            .map(real_time)
+            .transpose()?
+            .flatten()
+            .map(|duration| Gold { duration });
+    }
+
+    let pb_splits = segments
+        .iter()
+        .map(|segment| {
+            let split_time = child(*segment, "SplitTimes").and_then(|times| {
+                times.children().find(|node| {
+                    node.has_tag_name("SplitTime")
+                        && node.attribute("name") == Some("Personal Best")
+                })
+            });
+            let time = split_time.map(real_time).transpose()?.flatten();
+            Ok(time.map(|time| Split { time }))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // LiveSplit only keeps segment times, so the splits of each attempt are rebuilt from them
+    let attempt_ids = child(run, "AttemptHistory")
+        .into_iter()
+        .flat_map(|history| history.children())
+        .filter(|node| node.has_tag_name("Attempt"))
+        .filter_map(|node| node.attribute("id")?.parse::<u32>().ok());
+    for id in attempt_ids {
+        let splits = attempt_splits(&segments, id)?;
+        let completed = splits.len() == segments.len() && matches!(splits.last(), Some(Some(_)));
+        if completed {
+            split_file.completed += 1;
+        } else {
+            split_file.resets[splits.len().min(segments.len() - 1)] += 1;
+        }
+        split_file.record_segments(id, &splits);
+        split_file.attempt_history.push(Attempt { id, splits });
+    }
+
+    split_file.attempts = child_text(run, "AttemptCount").parse().unwrap_or(0).max(
+        split_file
+            .attempt_history
+            .iter()
+            .map(|a| a.id)
+            .max()
+            .unwrap_or(0),
+    );
+
+    if let Some(Some(pb_time)) = pb_splits.last().map(|s| s.as_ref().map(|s| s.time)) {
+        // The PB attempt is the completed attempt with the same final time, if it's still known
+        let attempt = split_file
+            .attempt_history
+            .iter()
+            .find(|attempt| {
+                attempt.splits.len() == segments.len()
+                    && attempt.splits.last().unwrap().as_ref().map(|s| s.time) == Some(pb_time)
+            })
+            .map_or(split_file.attempts, |attempt| attempt.id);
+        split_file.personal_best = Some(PersonalBest {
+            attempt,
+            splits: pb_splits,
+        });
+    }
+
+    Ok(split_file)
+}
+
+// Splits of one attempt from the segment history, ending at the first segment it didn't reach
+fn attempt_splits(segments: &[Node], id: u32) -> anyhow::Result<Vec<Option<Split>>> {
+    let id = id.to_string();
+    let mut splits = Vec::new();
+    let mut total = Duration::ZERO;
+    for segment in segments {
+        let Some(time) = child(*segment, "SegmentHistory").and_then(|history| {
+            history
+                .children()
+                .find(|node| node.has_tag_name("Time") && node.attribute("id") == Some(&id))
+        }) else {
+            break;
+        };
+        // A time without a duration is a skipped split, which the next segment's time includes
+        match real_time(time)? {
+            Some(duration) => {
+                total += duration;
+                splits.push(Some(Split { time: total }));
+            }
+            None => splits.push(None),
+        }
+    }
+    Ok(splits)
+}
+
+/// Writes a splits file in LiveSplit's `.lss` format.
+pub fn export_lss(split_file: &SplitFile) -> String {
+    let mut out = String::new();
+    let pb = split_file.personal_best.as_ref();
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<Run version=\"1.7.0\">\n");
+    out.push_str("  <GameIcon />\n");
+    let _ = writeln!(out, "  <GameName>{}</GameName>", escape(&split_file.title));
+    let _ = writeln!(
+        out,
+        "  <CategoryName>{}</CategoryName>",
+        escape(&split_file.category)
+    );
+    out.push_str("  <Offset>00:00:00</Offset>\n");
+    let _ = writeln!(
+        out,
+        "  <AttemptCount>{}</AttemptCount>",
+        split_file.attempts
+    );
+
+    out.push_str("  <AttemptHistory>\n");
+    for attempt in &split_file.attempt_history {
+        let finish = match attempt.splits.last() {
+            Some(Some(split)) if attempt.splits.len() == split_file.split_names.len() => {
+                Some(split.time)
+            }
+            _ => None,
+        };
+        match finish {
+            Some(time) => {
+                let _ = writeln!(
+                    out,
+                    "    <Attempt id=\"{}\"><RealTime>{}</RealTime></Attempt>",
+                    attempt.id,
+                    format_time(time)
+                );
+            }
+            None => {
+                let _ = writeln!(out, "    <Attempt id=\"{}\" />", attempt.id);
+            }
+        }
+    }
+    out.push_str("  </AttemptHistory>\n");
+
+    out.push_str("  <Segments>\n");
+    for (i, name) in split_file.split_names.iter().enumerate() {
+        out.push_str("    <Segment>\n");
+        let _ = writeln!(out, "      <Name>{}</Name>", escape(name));
+        out.push_str("      <Icon />\n");
+
+        out.push_str("      <SplitTimes>\n");
+        match pb.and_then(|pb| pb.splits[i].as_ref()) {
+            Some(split) => {
+                let _ = writeln!(
+                    out,
+                    "        <SplitTime name=\"Personal Best\"><RealTime>{}</RealTime></SplitTime>",
+                    format_time(split.time)
+                );
+            }
+            None => out.push_str("        <SplitTime name=\"Personal Best\" />\n"),
+        }
+        out.push_str("      </SplitTimes>\n");
+
+        match &split_file.golds[i] {
+            Some(gold) => {
+                let _ = writeln!(
+                    out,
+                    "      <BestSegmentTime><RealTime>{}</RealTime></BestSegmentTime>",
+                    format_time(gold.duration)
+                );
+            }
+            None => out.push_str("      <BestSegmentTime />\n"),
+        }
+
+        out.push_str("      <SegmentHistory>\n");
+        for seg in &split_file.segment_history[i] {
+            let _ = writeln!(
+                out,
+                "        <Time id=\"{}\"><RealTime>{}</RealTime></Time>",
+                seg.attempt,
+                format_time(seg.duration)
+            );
+        }
+        out.push_str("      </SegmentHistory>\n");
+        out.push_str("    </Segment>\n");
+    }
+    out.push_str("  </Segments>\n");
+    out.push_str("  <AutoSplitterSettings />\n");
+    out.push_str("</Run>\n");
+    out
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    child(node, name)
+        .and_then(|child| child.text())
+        .unwrap_or("")
+        .trim()
+}
+
+// The real time of a time element, which may be missing for skipped or unset times
+fn real_time(node: Node) -> anyhow::Result<Option<Duration>> {
+    match child(node, "RealTime").and_then(|time| time.text()) {
+        Some(text) => parse_time(text.trim())
+            .map(Some)
+            .ok_or_else(|| anyhow!("Invalid LiveSplit time: {}", text)),
+        None => Ok(None),
+    }
+}
+
+// LiveSplit times look like `[d.]hh:mm:ss[.fffffff]`
+fn parse_time(text: &str) -> Option<Duration> {
+    let (days, rest) = match text.split_once('.') {
+        Some((days, rest)) if !days.contains(':') => (days.parse::<u64>().ok()?, rest),
+        _ => (0, text),
+    };
+    let (hms, fraction) = rest.split_once('.').unwrap_or((rest, ""));
+
+    let parts: Vec<u64> = hms
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return None;
+    };
+
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        format!("{:0<9}", fraction).parse().ok()?
+    };
+
+    let secs = ((days * 24 + hours) * 60 + minutes) * 60 + seconds;
+    Some(Duration::new(secs, nanos))
+}
+
+fn format_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours) = (secs / 86400, secs / 3600 % 24);
+    let time = format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        hours,
+        secs / 60 % 60,
+        secs % 60,
+        duration.subsec_nanos() / 100
+    );
+    if days > 0 {
+        format!("{}.{}", days, time)
+    } else {
+        time
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -2,49 +2,384 @@ use anyhow::anyhow;
 use std::{
     path::PathBuf,
     thread,
-    time::{self, Duration},
+    time::{Duration, Instant},
 };
 
+use clap::{
+    error::ErrorKind, parser::ValueSource, ArgMatches, Args, CommandFactory, FromArgMatches,
+    Parser, Subcommand,
+};
+
+use commands::SplitEdit;
+use editor::Editor;
+use events::EventLog;
+use race::Race;
+use settings::{Column, Settings, ThemeName};
 use timer::Timer;
 
+mod audio;
+mod autosplitter;
 mod bigtext;
+mod commands;
+mod editor;
+mod events;
+mod hooks;
+mod input;
+mod livesplit;
+mod overlay;
+mod picker;
+mod practice;
+mod race;
 mod rotty;
+mod saving;
+mod server;
 mod settings;
 mod split_file;
+mod stats;
 mod timer;
 mod timer_state;
 mod utils;
 mod view;
 
-static TARGET_FPS: i32 = 60;
+static INPUT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Parser)]
+#[command(version, about = "A terminal speedrun timer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: RunArgs,
+
+    #[command(flatten)]
+    options: GlobalOptions,
+}
+
+#[derive(Args)]
+struct GlobalOptions {
+    /// Settings file to use instead of the default one
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Color theme, overriding the settings file
+    #[arg(long, global = true, value_enum)]
+    theme: Option<ThemeName>,
+
+    /// Frames rendered per second while the timer is running, overriding the settings file
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..=240))]
+    fps: Option<u32>,
+
+    /// Redraw at the same rate even when nothing on screen is changing
+    #[arg(long, global = true)]
+    no_adaptive_fps: bool,
+
+    /// Comma-separated split columns, overriding the settings file
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "COLUMNS"
+    )]
+    layout: Option<Vec<Column>>,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Splits file, or directory to pick one from
+    #[arg(default_value = ".")]
+    path: PathBuf,
+
+    /// Append each timer event to a file as a line of JSON
+    #[arg(long, value_name = "PATH")]
+    event_log: Option<PathBuf>,
+
+    /// Start in practice mode, where runs aren't saved to the splits file
+    #[arg(long)]
+    practice: bool,
+
+    /// Practice from this split on, starting at 1, instead of running the whole run
+    #[arg(long, value_name = "POSITION")]
+    from_split: Option<usize>,
+
+    /// Practice up to and including this split
+    #[arg(long, value_name = "POSITION")]
+    to_split: Option<usize>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the timer (the default)
+    Run(RunArgs),
+    /// Create a splits file, prompting for anything not given
+    New {
+        path: PathBuf,
+        split_names: Vec<String>,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        category: Option<String>,
+        /// File with one split name per line
+        #[arg(long, value_name = "NAMES_FILE")]
+        splits: Option<PathBuf>,
+    },
+    /// Edit a splits file interactively
+    Edit { path: PathBuf },
+    /// Report problems in a splits file
+    Check {
+        path: PathBuf,
+        /// Offer to repair each problem
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Print statistics for each segment
+    Stats {
+        path: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Rebuild golds from the best times in the PB and history
+    RecomputeGolds {
+        path: PathBuf,
+        /// Only show which golds would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Insert, remove, merge or rename splits
+    #[command(subcommand)]
+    Split(SplitCommand),
+    /// Convert a LiveSplit .lss file into a new splits file
+    Import { lss_path: PathBuf, path: PathBuf },
+    /// Write a splits file as a LiveSplit .lss file
+    Export { path: PathBuf, lss_path: PathBuf },
+    /// Show what the autosplitter would do, without running the timer
+    AutosplitterDryRun {
+        path: PathBuf,
+        /// Also match the lines already in the log, for log file autosplitters
+        #[arg(long)]
+        from_start: bool,
+    },
+    /// Race several runners at once, each with their own splits file and hotkeys
+    Race {
+        #[arg(required = true, num_args = 2..)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SplitCommand {
+    /// Insert a split before the given position
+    Insert {
+        path: PathBuf,
+        position: usize,
+        name: String,
+        /// Allow removing the PB, which has no time for a split inserted at the end
+        #[arg(long)]
+        drop_pb: bool,
+    },
+    /// Remove a split, merging its segment into the next one
+    Remove { path: PathBuf, position: usize },
+    /// Merge a segment with the next one
+    Merge {
+        path: PathBuf,
+        position: usize,
+        name: Option<String>,
+    },
+    /// Rename a split
+    Rename {
+        path: PathBuf,
+        position: usize,
+        name: String,
+    },
+}
+
+// The timer's arguments are accepted before any subcommand, where they'd do nothing, so a
+// mistyped command fails instead of running without them
+fn check_run_args_unused(matches: &ArgMatches) {
+    let Some((name, _)) = matches.subcommand() else {
+        return;
+    };
+    let mut run_args = RunArgs::augment_args(clap::Command::new("run"));
+    run_args.build();
+    let given = run_args
+        .get_arguments()
+        .find(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine));
+    if let Some(arg) = given {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("'{}' can't be used with the '{}' subcommand", arg, name),
+            )
+            .exit();
+    }
+}
 
 fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        return Err(anyhow!("Usage: {} <path_to_splits_file>", args[0]));
+    let matches = Cli::command().get_matches();
+    check_run_args_unused(&matches);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    match cli.command {
+        None => run(&cli.run, &cli.options),
+        Some(Command::Run(run_args)) => run(&run_args, &cli.options),
+        Some(Command::New {
+            path,
+            split_names,
+            title,
+            category,
+            splits,
+        }) => commands::new_split_file(&path, title, category, splits.as_deref(), split_names),
+        Some(Command::Edit { path }) => {
+            let settings = load_settings(&cli.options)?;
+            Editor::new(&path, settings.theme)?.run()
+        }
+        Some(Command::Check { path, fix }) => commands::check_split_file(&path, fix),
+        Some(Command::Stats { path, json }) => commands::print_stats(&path, json),
+        Some(Command::RecomputeGolds { path, dry_run }) => {
+            commands::recompute_golds(&path, dry_run)
+        }
+        Some(Command::Split(command)) => {
+            let (path, edit) = match command {
+                SplitCommand::Insert {
+                    path,
+                    position,
+                    name,
+                    drop_pb,
+                } => (
+                    path,
+                    SplitEdit::Insert {
+                        position,
+                        name,
+                        drop_pb,
+                    },
+                ),
+                SplitCommand::Remove { path, position } => (path, SplitEdit::Remove { position }),
+                SplitCommand::Merge {
+                    path,
+                    position,
+                    name,
+                } => (path, SplitEdit::Merge { position, name }),
+                SplitCommand::Rename {
+                    path,
+                    position,
+                    name,
+                } => (path, SplitEdit::Rename { position, name }),
+            };
+            commands::edit_splits(&path, edit)
+        }
+        Some(Command::Import { lss_path, path }) => commands::import_lss(&lss_path, &path),
+        Some(Command::Export { path, lss_path }) => commands::export_lss(&path, &lss_path),
+        Some(Command::AutosplitterDryRun { path, from_start }) => {
+            commands::dry_run_autosplitter(&path, from_start)
+        }
+        Some(Command::Race { paths }) => race(&paths, &cli.options),
     }
-    let path = PathBuf::from(&args[1]);
+}
 
-    let config_path = PathBuf::from(std::env::var("HOME").unwrap())
-        .join(".config")
-        .join("flitter-timer")
-        .join("config.json");
+// Settings from the settings file, with the command line options applied on top
+fn load_settings(options: &GlobalOptions) -> anyhow::Result<Settings> {
+    let mut settings = match &options.config {
+        Some(path) if !path.exists() => {
+            return Err(anyhow!("Settings file {} does not exist", path.display()))
+        }
+        Some(path) => settings::load_settings(path)?,
+        None => match settings::default_config_path() {
+            Some(path) => settings::load_settings(&path)?,
+            None => settings::DEFAULT_SETTINGS.clone(),
+        },
+    };
 
-    let mut timer = Timer::new(&path, &config_path)?;
+    if let Some(theme) = options.theme {
+        settings.theme = settings::get_theme(theme);
+    }
+    if let Some(columns) = &options.layout {
+        settings.columns = columns.clone();
+    }
+    if let Some(fps) = options.fps {
+        settings.fps = fps;
+    }
+    if options.no_adaptive_fps {
+        settings.adaptive_fps = false;
+    }
+    Ok(settings)
+}
+
+fn run(run_args: &RunArgs, options: &GlobalOptions) -> anyhow::Result<()> {
+    let settings = load_settings(options)?;
+
+    // Without a splits file, pick one from the given directory
+    let mut path = run_args.path.clone();
+    if path.is_dir() {
+        match picker::pick_split_file(&path, settings.theme)? {
+            Some(picked) => path = picked,
+            None => return Ok(()),
+        }
+    }
+
+    let mut timer = Timer::new(&path, settings)?;
+    if let Some(event_log) = &run_args.event_log {
+        timer.subscribe(EventLog::open(event_log)?);
+    }
+    timer.set_practice(run_args.practice);
+    if run_args.from_split.is_some() || run_args.to_split.is_some() {
+        timer.set_segment_practice(run_args.from_split, run_args.to_split)?;
+    }
 
-    let target_frame_time = Duration::from_secs_f32(1.0 / (TARGET_FPS as f32));
-    let mut frame_time = target_frame_time;
+    // Hotkeys are timestamped on their own thread, but are only applied here, so check often
+    // to keep the display responsive even when redrawing rarely
+    let mut next_frame = Instant::now();
     loop {
-        let start_instant = time::Instant::now();
-        let delta_seconds = target_frame_time.max(frame_time).as_secs_f32();
-        if !timer.update(delta_seconds)? {
+        if !timer.handle_input()? {
             break;
         }
 
-        frame_time = start_instant.elapsed();
-        if frame_time < target_frame_time {
-            thread::sleep(target_frame_time - frame_time);
+        let now = Instant::now();
+        if timer.needs_redraw() || now >= next_frame {
+            timer.render()?;
+            next_frame = now + timer.frame_interval();
         }
+
+        let until_frame = next_frame.saturating_duration_since(Instant::now());
+        thread::sleep(until_frame.min(INPUT_POLL_INTERVAL));
+    }
+
+    if let Some(err) = timer.take_autosplitter_error() {
+        return Err(err.context("The autosplitter stopped during the session"));
+    }
+    if let Some(err) = timer.take_listener_errors().into_iter().next() {
+        return Err(err.context("An event listener failed during the session"));
+    }
+
+    Ok(())
+}
+
+fn race(paths: &[PathBuf], options: &GlobalOptions) -> anyhow::Result<()> {
+    let settings = load_settings(options)?;
+    let mut race = Race::new(paths, settings)?;
+
+    let mut next_frame = Instant::now();
+    loop {
+        if !race.handle_input()? {
+            break;
+        }
+
+        let now = Instant::now();
+        if race.needs_redraw() || now >= next_frame {
+            race.render()?;
+            next_frame = now + race.frame_interval();
+        }
+
+        let until_frame = next_frame.saturating_duration_since(Instant::now());
+        thread::sleep(until_frame.min(INPUT_POLL_INTERVAL));
+    }
+
+    // Every runner's results are already saved, so one failure is as good as another to report
+    if let Some(err) = race.take_autosplitter_errors().into_iter().next() {
+        return Err(err.context("An autosplitter stopped during the race"));
+    }
+    if let Some(err) = race.take_listener_errors().into_iter().next() {
+        return Err(err.context("An event listener failed during the race"));
     }
 
     Ok(())
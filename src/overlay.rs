@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::timer_state::{TimerMode, TimerState};
+use crate::utils::{format_duration, get_elapsed, get_run_summary, get_sum_of_best, Prefix, Sign};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayFormat {
+    // One text file per value in the `path` directory
+    Files,
+    // A single JSON file at `path`
+    Json,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct OverlaySettings {
+    pub format: OverlayFormat,
+    pub path: PathBuf,
+}
+
+/// The values shown by the overlay, as they'd appear in the timer. Missing values are None.
+#[derive(Serialize, Clone, PartialEq, Eq)]
+pub struct OverlayState {
+    pub timer: String,
+    pub split_name: String,
+    pub delta: Option<String>,
+    pub previous_segment: Option<String>,
+    pub sum_of_best: Option<String>,
+    pub personal_best: Option<String>,
+}
+
+impl OverlayState {
+    fn fields(&self) -> [(&'static str, Option<&str>); 6] {
+        [
+            ("timer", Some(self.timer.as_str())),
+            ("split_name", Some(self.split_name.as_str())),
+            ("delta", self.delta.as_deref()),
+            ("previous_segment", self.previous_segment.as_deref()),
+            ("sum_of_best", self.sum_of_best.as_deref()),
+            ("personal_best", self.personal_best.as_deref()),
+        ]
+    }
+}
+
+pub fn get_overlay_state(timer: &TimerState) -> OverlayState {
+    let summary = get_run_summary(timer);
+    let split_names = &timer.split_file.split_names;
+    let fmt_dur = |dur| format_duration(dur, 2, Sign::Positive, Prefix::NoneOrMinus);
+    let fmt_signed = |dur, neg: bool| format_duration(dur, 2, (!neg).into(), Prefix::PlusOrMinus);
+
+    // The split being run, or the last one once the run is over
+    let curr_idx = match timer.mode {
+        TimerMode::Finished { .. } => timer.splits.len() - 1,
+        _ => timer.splits.len(),
+    };
+    let prev_seg = timer.splits.len().checked_sub(1).map(|idx| &summary[idx]);
+
+    let (sob, missing) = get_sum_of_best(&summary);
+    let personal_best = timer
+        .split_file
+        .personal_best
+        .as_ref()
+        .and_then(|pb| pb.splits.last()?.as_ref().map(|split| split.time));
+
+    OverlayState {
+        timer: fmt_dur(get_elapsed(timer)),
+        split_name: split_names[curr_idx].clone(),
+        delta: prev_seg.and_then(|seg| Some(fmt_signed(seg.live_delta?, seg.live_delta_neg))),
+        previous_segment: prev_seg.and_then(|seg| Some(fmt_signed(seg.gained?, seg.gained_neg))),
+        sum_of_best: (missing == 0).then(|| fmt_dur(sob)),
+        personal_best: personal_best.map(fmt_dur),
+    }
+}
+
+// The timer changes every frame while running, so changes to it alone are written at most this
+// often
+static TIMER_WRITE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Writes the overlay values to files for streaming software to show, whenever they change.
+pub struct Overlay {
+    settings: OverlaySettings,
+    prev_state: Option<OverlayState>,
+    next_timer_write: Instant,
+}
+
+impl Overlay {
+    pub fn new(settings: OverlaySettings) -> anyhow::Result<Self> {
+        if settings.format == OverlayFormat::Files {
+            std::fs::create_dir_all(&settings.path).with_context(|| {
+                format!(
+                    "Failed to create overlay directory {}",
+                    settings.path.display()
+                )
+            })?;
+        }
+        Ok(Self {
+            settings,
+            prev_state: None,
+            next_timer_write: Instant::now(),
+        })
+    }
+
+    pub fn update(&mut self, timer: &TimerState) -> anyhow::Result<()> {
+        let state = get_overlay_state(timer);
+        if self.prev_state.as_ref() == Some(&state) {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let only_timer_changed = self
+            .prev_state
+            .as_ref()
+            .is_some_and(|prev| prev.fields()[1..] == state.fields()[1..]);
+        if only_timer_changed && now < self.next_timer_write {
+            return Ok(());
+        }
+        self.next_timer_write = now + TIMER_WRITE_INTERVAL;
+
+        match self.settings.format {
+            OverlayFormat::Files => {
+                let prev_fields = self.prev_state.as_ref().map(OverlayState::fields);
+                for (i, (name, value)) in state.fields().into_iter().enumerate() {
+                    if prev_fields.is_some_and(|prev| prev[i].1 == value) {
+                        continue;
+                    }
+                    let path = self.settings.path.join(format!("{}.txt", name));
+                    write_atomic(&path, value.unwrap_or(""))?;
+                }
+            }
+            OverlayFormat::Json => {
+                write_atomic(&self.settings.path, &serde_json::to_string(&state)?)?;
+            }
+        }
+
+        self.prev_state = Some(state);
+        Ok(())
+    }
+}
+
+// Streaming software may read the file at any time, so never leave it half written
+fn write_atomic(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::Attribute;
+
+use crate::rotty::{Block, Image, Renderer, TextAlign};
+use crate::settings::Theme;
+use crate::split_file::read_split_file;
+use crate::utils::{format_duration, parse_color, Prefix, Sign};
+
+static TITLE_WIDTH: u16 = 28;
+static CATEGORY_WIDTH: u16 = 24;
+static COL_WIDTH: u16 = 12;
+static PICKER_WIDTH: u16 = TITLE_WIDTH + CATEGORY_WIDTH + COL_WIDTH * 2;
+
+pub struct SplitFileEntry {
+    pub path: PathBuf,
+    pub title: String,
+    pub category: String,
+    pub attempts: u32,
+    pub completed: u32,
+    pub pb: Option<Duration>,
+}
+
+/// Lists the valid splits files in a directory, skipping anything that doesn't load.
+pub fn find_split_files(dir: &Path) -> anyhow::Result<Vec<SplitFileEntry>> {
+    let mut entries = Vec::new();
+    let read_dir =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+
+    for dir_entry in read_dir {
+        let path = dir_entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Ok(split_file) = read_split_file(&path) else {
+            continue;
+        };
+
+        let pb = split_file
+            .personal_best
+            .as_ref()
+            .and_then(|pb| pb.splits.last()?.as_ref().map(|s| s.time));
+        entries.push(SplitFileEntry {
+            path,
+            title: split_file.title,
+            category: split_file.category,
+            attempts: split_file.attempts,
+            completed: split_file.completed,
+            pb,
+        });
+    }
+
+    entries.sort_by(|a, b| (&a.title, &a.category).cmp(&(&b.title, &b.category)));
+    Ok(entries)
+}
+
+pub enum PickerChoice {
+    Open(PathBuf),
+    Cancel,
+}
+
+/// A list of splits files to choose from.
+pub struct Picker {
+    dir: PathBuf,
+    entries: Vec<SplitFileEntry>,
+    selected: usize,
+    // Why the last file chosen couldn't be opened
+    error: Option<String>,
+}
+
+impl Picker {
+    pub fn new(dir: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            dir: dir.to_owned(),
+            entries: find_split_files(dir)?,
+            selected: 0,
+            error: None,
+        })
+    }
+
+    /// Opens the picker with the given file selected, if it's in the list.
+    pub fn with_selected(mut self, path: &Path) -> Self {
+        let path = path.canonicalize().ok();
+        if let Some(idx) = self
+            .entries
+            .iter()
+            .position(|entry| entry.path.canonicalize().ok() == path)
+        {
+            self.selected = idx;
+        }
+        self
+    }
+
+    /// Shows why the chosen file couldn't be opened, until another key is pressed.
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> Option<PickerChoice> {
+        self.error = None;
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1))
+            }
+            KeyCode::Enter => {
+                let entry = self.entries.get(self.selected)?;
+                return Some(PickerChoice::Open(entry.path.clone()));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(PickerChoice::Cancel),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, theme: &Theme) -> Block {
+        let label_color = parse_color(theme.label_text);
+
+        let heading = Image::new(
+            &format!("Splits in {}", self.dir.display()),
+            PICKER_WIDTH,
+            TextAlign::Left,
+        )
+        .attr(Attribute::Bold)
+        .build();
+
+        let headers = [
+            ("Title", TITLE_WIDTH, TextAlign::Left),
+            ("Category", CATEGORY_WIDTH, TextAlign::Left),
+            ("Attempts", COL_WIDTH, TextAlign::Right),
+            ("PB", COL_WIDTH, TextAlign::Right),
+        ]
+        .map(|(h, width, align)| Image::new(h, width, align).fg_color(label_color).build());
+        let line_sep = Image::new(
+            &"─".repeat(PICKER_WIDTH as usize),
+            PICKER_WIDTH,
+            TextAlign::Left,
+        )
+        .fg_color(label_color)
+        .build();
+
+        let mut sections = vec![heading, Block::hcat(headers), line_sep];
+
+        if self.entries.is_empty() {
+            sections.push(
+                Image::new("No splits files found", PICKER_WIDTH, TextAlign::Left)
+                    .fg_color(label_color)
+                    .build(),
+            );
+        }
+        for (i, entry) in self.entries.iter().enumerate() {
+            let pb_text = match entry.pb {
+                Some(pb) => format_duration(pb, 2, Sign::Positive, Prefix::NoneOrMinus),
+                None => "-".to_string(),
+            };
+            let row = Block::hcat([
+                Image::new(&entry.title, TITLE_WIDTH, TextAlign::Left).build(),
+                Image::new(&entry.category, CATEGORY_WIDTH, TextAlign::Left).build(),
+                Image::new(
+                    &format!("{}/{}", entry.completed, entry.attempts),
+                    COL_WIDTH,
+                    TextAlign::Right,
+                )
+                .build(),
+                Image::new(&pb_text, COL_WIDTH, TextAlign::Right).build(),
+            ]);
+
+            let mut bg = Image::new(
+                &" ".repeat(PICKER_WIDTH as usize),
+                PICKER_WIDTH,
+                TextAlign::Left,
+            );
+            if i == self.selected {
+                bg = bg.bg_color(parse_color(theme.highlight));
+            }
+            sections.push(bg.build().stack(row));
+        }
+
+        if let Some(error) = &self.error {
+            sections.push(
+                Image::new(error, PICKER_WIDTH, TextAlign::Left)
+                    .fg_color(parse_color(theme.behind_lose))
+                    .build(),
+            );
+        }
+        sections.push(
+            Image::new(
+                "Up/Down: select   Enter: open   q: cancel",
+                PICKER_WIDTH,
+                TextAlign::Left,
+            )
+            .fg_color(label_color)
+            .build(),
+        );
+        Block::vcat(sections)
+    }
+}
+
+/// Shows a picker for the splits files in a directory until one is chosen or it's cancelled.
+pub fn pick_split_file(dir: &Path, theme: &Theme) -> anyhow::Result<Option<PathBuf>> {
+    let mut picker = Picker::new(dir)?;
+    let mut renderer = Renderer::new();
+    renderer.set_default_colors(parse_color(theme.normal_text), parse_color(theme.bg));
+
+    loop {
+        renderer.render(&picker.render(theme))?;
+
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match picker.handle_key(key.code) {
+                Some(PickerChoice::Open(path)) => return Ok(Some(path)),
+                Some(PickerChoice::Cancel) => return Ok(None),
+                None => {}
+            }
+        }
+    }
+}
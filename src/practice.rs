@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde_json::json;
+
+use crate::events::{format_time, EventListener, TimerEvent};
+use crate::split_file::{get_segment, Split};
+use crate::timer_state::{TimerMode, TimerState};
+
+/// Appends each saved practice run to a file as a line of JSON, since practice runs are kept out
+/// of the splits file.
+pub struct PracticeLog {
+    file: File,
+}
+
+impl PracticeLog {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open practice log {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl EventListener for PracticeLog {
+    fn on_event(&mut self, event: &TimerEvent, timer: &TimerState) -> anyhow::Result<()> {
+        let TimerEvent::Reset { saved: true } = event else {
+            return Ok(());
+        };
+        if !timer.practice {
+            return Ok(());
+        }
+
+        let splits: Vec<Option<Split>> = timer
+            .splits
+            .iter()
+            .map(|split| split.map(|time| Split { time }))
+            .collect();
+        // Splits before segment practice only hold the time it started from
+        let split_entries: Vec<_> = splits
+            .iter()
+            .enumerate()
+            .skip(timer.first_split())
+            .map(|(i, split)| {
+                json!({
+                    "name": timer.split_file.split_names[i],
+                    "time": split.as_ref().map(|split| format_time(split.time)),
+                    "segment": get_segment(&splits, i).map(format_time),
+                })
+            })
+            .collect();
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let line = json!({
+            "timestamp": timestamp,
+            "title": timer.split_file.title,
+            "category": timer.split_file.category,
+            "finished": matches!(timer.mode, TimerMode::Finished { .. }),
+            "splits": split_entries,
+        });
+        writeln!(self.file, "{}", line)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::Color;
+
+use crate::input;
+use crate::rotty::Renderer;
+use crate::settings::{Action, Settings};
+use crate::timer::Timer;
+use crate::timer_state::TimerMode;
+use crate::utils::parse_color;
+
+mod view;
+
+/// Several runners racing side by side, each with their own splits file and hotkeys. Every
+/// runner's results are saved to their own file, the same as running the timer alone.
+pub struct Race {
+    runners: Vec<Runner>,
+    renderer: Renderer,
+    settings: Settings,
+    redraw: bool,
+}
+
+struct Runner {
+    // Taken from the splits file's name, since runners of the same game share a title
+    name: String,
+    timer: Timer,
+}
+
+impl Race {
+    pub fn new(paths: &[PathBuf], settings: Settings) -> anyhow::Result<Self> {
+        if settings.race_hotkeys.len() < paths.len() {
+            return Err(anyhow!(
+                "A race of {} runners needs as many sets of race_hotkeys, but the settings have {}",
+                paths.len(),
+                settings.race_hotkeys.len()
+            ));
+        }
+
+        // There's no picker in a race
+        let hotkey_sets: Vec<_> = settings.race_hotkeys[..paths.len()]
+            .iter()
+            .map(|hotkeys| {
+                let mut hotkeys = hotkeys.clone();
+                hotkeys.retain(|_, action| *action != Action::SwitchSplitFile);
+                hotkeys
+            })
+            .collect();
+        // A key shared by two runners would act for both
+        let mut runner_for_key = HashMap::new();
+        for (i, hotkeys) in hotkey_sets.iter().enumerate() {
+            for &key in hotkeys.keys() {
+                if let Some(other) = runner_for_key.insert(key, i) {
+                    return Err(anyhow!(
+                        "Key {} is in the race_hotkeys of both runner {} and runner {}",
+                        key,
+                        other + 1,
+                        i + 1
+                    ));
+                }
+            }
+        }
+        // One thread polls the keyboard for everyone
+        let receivers = input::spawn_shared_hotkey_thread(hotkey_sets);
+
+        let runners = paths
+            .iter()
+            .zip(receivers)
+            .map(|(path, hotkeys)| {
+                // Only one overlay or server could be used
+                let mut runner_settings = settings.clone();
+                runner_settings.overlay = None;
+                runner_settings.server = None;
+
+                let timer = Timer::with_hotkeys(path, runner_settings, hotkeys)
+                    .with_context(|| format!("Failed to set up runner for {}", path.display()))?;
+                let name = path
+                    .file_stem()
+                    .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+                Ok(Runner { name, timer })
+            })
+            .collect::<anyhow::Result<Vec<Runner>>>()?;
+
+        // Places are worked out by comparing split positions
+        let split_counts: Vec<usize> = runners
+            .iter()
+            .map(|runner| runner.timer.state().split_file.split_names.len())
+            .collect();
+        if split_counts.windows(2).any(|pair| pair[0] != pair[1]) {
+            return Err(anyhow!(
+                "Every splits file in a race needs the same number of splits, but they have {}",
+                split_counts
+                    .iter()
+                    .map(|count| count.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        Ok(Self {
+            runners,
+            renderer: Renderer::new(),
+            settings,
+            redraw: true,
+        })
+    }
+
+    /// Handles terminal keys and every runner's hotkeys. Returns false once the race should quit.
+    pub fn handle_input(&mut self) -> anyhow::Result<bool> {
+        let mut pressed = Vec::new();
+        while event::poll(Duration::from_secs(0))? {
+            let event = event::read()?;
+            if let Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event
+            {
+                pressed.push(code);
+            }
+            self.redraw = true;
+        }
+
+        if pressed.contains(&KeyCode::Char('q')) {
+            return Ok(false);
+        }
+        if pressed.contains(&KeyCode::Char(' ')) {
+            self.start()?;
+        }
+
+        for runner in &mut self.runners {
+            runner.timer.update()?;
+            if runner.timer.take_redraw() {
+                self.redraw = true;
+            }
+        }
+        Ok(true)
+    }
+
+    // Starts everyone who isn't running yet at the same moment
+    fn start(&mut self) -> anyhow::Result<()> {
+        let now = Instant::now();
+        for runner in &mut self.runners {
+            if let TimerMode::Initial = runner.timer.state().mode {
+                runner.timer.apply_action(Action::Split, now)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Why each runner's autosplitter stopped, for those that failed during the race.
+    pub fn take_autosplitter_errors(&mut self) -> Vec<anyhow::Error> {
+        self.runners
+            .iter_mut()
+            .filter_map(|runner| {
+                let err = runner.timer.take_autosplitter_error()?;
+                Some(err.context(format!("Runner {}", runner.name)))
+            })
+            .collect()
+    }
+
+    /// The first error from each of the runners' event listeners that failed.
+    pub fn take_listener_errors(&mut self) -> Vec<anyhow::Error> {
+        self.runners
+            .iter_mut()
+            .flat_map(|runner| {
+                let name = runner.name.clone();
+                let errors = runner.timer.take_listener_errors();
+                errors
+                    .into_iter()
+                    .map(move |err| err.context(format!("Runner {}", name)))
+            })
+            .collect()
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.redraw
+    }
+
+    /// Time until the next frame, as often as the busiest runner needs.
+    pub fn frame_interval(&self) -> Duration {
+        self.runners
+            .iter()
+            .map(|runner| runner.timer.frame_interval())
+            .min()
+            .unwrap_or(Duration::from_secs(1))
+    }
+
+    pub fn render(&mut self) -> anyhow::Result<()> {
+        self.redraw = false;
+
+        let bg_color = if self.settings.draw_background {
+            parse_color(self.settings.theme.bg)
+        } else {
+            Color::Reset
+        };
+        self.renderer
+            .set_default_colors(parse_color(self.settings.theme.normal_text), bg_color);
+
+        let block = view::render_race_view(&self.runners, self.settings.theme);
+        self.renderer.render(&block)
+    }
+}
@@ -0,0 +1,164 @@
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use crossterm::style::Attribute;
+
+use crate::{
+    rotty::{Block, Image, TextAlign},
+    settings::Theme,
+    timer_state::{TimerMode, TimerState},
+    utils::{format_duration, get_elapsed, parse_color, Prefix, Sign},
+};
+
+use super::Runner;
+
+static POSITION_WIDTH: u16 = 4;
+static NAME_WIDTH: u16 = 16;
+static SPLIT_WIDTH: u16 = 20;
+static COL_WIDTH: u16 = 12;
+static WIDTH: u16 = POSITION_WIDTH + NAME_WIDTH + SPLIT_WIDTH + COL_WIDTH * 2;
+
+pub fn render_race_view(runners: &[Runner], theme: &Theme) -> Block {
+    let standings = get_standings(runners);
+    let leader = standings.first().map(|&i| runners[i].timer.state());
+
+    let title = match runners.first() {
+        Some(runner) => {
+            let split_file = &runner.timer.state().split_file;
+            format!("{} - {}", split_file.title, split_file.category)
+        }
+        None => String::new(),
+    };
+    let title_block = Image::new("Race", WIDTH, TextAlign::Center)
+        .attr(Attribute::Bold)
+        .build();
+    let subtitle_block = Image::new(&title, WIDTH, TextAlign::Center)
+        .attr(Attribute::Bold)
+        .build();
+
+    let spacer_block = Image::new(&" ".repeat(WIDTH as usize), WIDTH, TextAlign::Left).build();
+
+    let headers = [
+        ("#", POSITION_WIDTH, TextAlign::Left),
+        ("Runner", NAME_WIDTH, TextAlign::Left),
+        ("Split", SPLIT_WIDTH, TextAlign::Left),
+        ("Time", COL_WIDTH, TextAlign::Right),
+        ("Gap", COL_WIDTH, TextAlign::Right),
+    ]
+    .map(|(header, width, align)| {
+        Image::new(header, width, align)
+            .fg_color(parse_color(theme.label_text))
+            .build()
+    });
+    let header_row = Block::hcat(headers);
+
+    let line_sep = Image::new(&"─".repeat(WIDTH as usize), WIDTH, TextAlign::Left)
+        .fg_color(parse_color(theme.label_text))
+        .build();
+
+    let runner_rows = standings
+        .iter()
+        .enumerate()
+        .map(|(position, &i)| get_runner_row(&runners[i], position, leader, theme));
+
+    let all_waiting = runners
+        .iter()
+        .all(|runner| matches!(runner.timer.state().mode, TimerMode::Initial));
+    let hint = if all_waiting {
+        "Space to start everyone, q to quit"
+    } else {
+        "q to quit"
+    };
+    let hint_block = Image::new(hint, WIDTH, TextAlign::Left)
+        .fg_color(parse_color(theme.label_text))
+        .build();
+
+    let mut sections = vec![
+        title_block,
+        subtitle_block,
+        spacer_block.clone(),
+        header_row,
+        line_sep.clone(),
+    ];
+    sections.extend(runner_rows);
+    sections.extend([line_sep, spacer_block, hint_block]);
+    Block::vcat(sections)
+}
+
+// Indices of the runners from first to last place: finished runners by their final time, then
+// whoever is furthest along, with ties going to whoever reached their last split first
+fn get_standings(runners: &[Runner]) -> Vec<usize> {
+    let mut standings: Vec<usize> = (0..runners.len()).collect();
+    standings.sort_by_key(|&i| {
+        let timer = runners[i].timer.state();
+        let last_split = timer.splits.last().copied().flatten();
+        match timer.mode {
+            TimerMode::Finished { .. } => (0, Reverse(0), last_split.unwrap_or(Duration::MAX)),
+            TimerMode::Running { .. } | TimerMode::Paused { .. } => (
+                1,
+                Reverse(timer.splits.len()),
+                last_split.unwrap_or(Duration::MAX),
+            ),
+            TimerMode::Initial => (2, Reverse(0), Duration::ZERO),
+        }
+    });
+    standings
+}
+
+fn get_runner_row(
+    runner: &Runner,
+    position: usize,
+    leader: Option<&TimerState>,
+    theme: &Theme,
+) -> Block {
+    let timer = runner.timer.state();
+
+    let split_text = match timer.mode {
+        TimerMode::Initial => "-",
+        TimerMode::Finished { .. } => "Finished",
+        TimerMode::Running { .. } | TimerMode::Paused { .. } => timer
+            .split_file
+            .split_names
+            .get(timer.splits.len())
+            .map_or("-", String::as_str),
+    };
+
+    let time_text = format_duration(get_elapsed(timer), 2, Sign::Positive, Prefix::NoneOrMinus);
+    let time_color = match timer.mode {
+        TimerMode::Initial | TimerMode::Paused { .. } => parse_color(theme.paused_text),
+        TimerMode::Finished { .. } if position == 0 => parse_color(theme.ahead_gain),
+        TimerMode::Running { .. } | TimerMode::Finished { .. } => parse_color(theme.normal_text),
+    };
+
+    let gap_text = match leader {
+        Some(leader) if position > 0 => get_gap(timer, leader).unwrap_or_else(|| "-".to_string()),
+        _ => String::new(),
+    };
+
+    Block::hcat([
+        Image::new(&(position + 1).to_string(), POSITION_WIDTH, TextAlign::Left).build(),
+        Image::new(&runner.name, NAME_WIDTH, TextAlign::Left)
+            .attr(Attribute::Bold)
+            .build(),
+        Image::new(split_text, SPLIT_WIDTH, TextAlign::Left).build(),
+        Image::new(&time_text, COL_WIDTH, TextAlign::Right)
+            .fg_color(time_color)
+            .build(),
+        Image::new(&gap_text, COL_WIDTH, TextAlign::Right)
+            .fg_color(parse_color(theme.behind_lose))
+            .build(),
+    ])
+}
+
+// How far behind the leader a runner was at the last split they reached
+fn get_gap(timer: &TimerState, leader: &TimerState) -> Option<String> {
+    let idx = timer.splits.len().checked_sub(1)?;
+    let time = timer.splits[idx]?;
+    let leader_time = leader.splits.get(idx).copied().flatten()?;
+    let text = if time >= leader_time {
+        format_duration(time - leader_time, 2, Sign::Positive, Prefix::PlusOrMinus)
+    } else {
+        format_duration(leader_time - time, 2, Sign::Negative, Prefix::PlusOrMinus)
+    };
+    Some(text)
+}
@@ -196,6 +196,10 @@ impl Renderer {
 
 impl Drop for Renderer {
     fn drop(&mut self) {
+        // Nothing to restore if this renderer never drew anything
+        if !self.initialized {
+            return;
+        }
         self.stdout
             .execute(ResetColor)
             .unwrap()
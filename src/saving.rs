@@ -0,0 +1,120 @@
+use anyhow::Context;
+
+use crate::events::{EventRecorder, TimerEvent};
+use crate::split_file::{write_split_file, Attempt, Gold, PersonalBest, Split};
+use crate::timer_state::{TimerMode, TimerState};
+use crate::utils::get_run_summary;
+
+/// Records each reset run in the splits file, or just counts the reset if it's discarded.
+/// Practice runs are left out of the file.
+pub struct RunSaver;
+
+impl EventRecorder for RunSaver {
+    fn on_event(&mut self, event: &TimerEvent, timer: &mut TimerState) -> anyhow::Result<()> {
+        let TimerEvent::Reset { saved } = *event else {
+            return Ok(());
+        };
+        if timer.practice {
+            return Ok(());
+        }
+        save_run(timer, saved).context("Failed to save the run")
+    }
+}
+
+fn save_run(timer: &mut TimerState, saved: bool) -> anyhow::Result<()> {
+    // Segment practice only adds to the segment history and golds
+    if let Some(range) = &timer.segment_practice {
+        if saved {
+            let splits = run_splits(timer);
+            timer
+                .split_file
+                .record_practice_segments(range.start, &splits);
+            save_golds(timer)?;
+        }
+        return Ok(());
+    }
+
+    let finished = matches!(timer.mode, TimerMode::Finished { .. });
+    match (saved, finished) {
+        (true, true) => {
+            timer.split_file.attempts += 1;
+            timer.split_file.completed += 1;
+            record_attempt(timer);
+            save_golds(timer)?; // Also saves attempts/completed
+            save_personal_best(timer)?;
+        }
+        (true, false) => {
+            timer.split_file.attempts += 1;
+            record_attempt(timer);
+            record_reset(timer);
+            save_golds(timer)?; // Also saves attempts and resets
+        }
+        (false, true) => {}
+        (false, false) => {
+            record_reset(timer);
+            write_split_file(&timer.split_file)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_splits(timer: &TimerState) -> Vec<Option<Split>> {
+    timer
+        .splits
+        .iter()
+        .map(|s| s.map(|dur| Split { time: dur }))
+        .collect()
+}
+
+fn record_attempt(timer: &mut TimerState) {
+    let attempt = Attempt {
+        id: timer.split_file.attempts,
+        splits: run_splits(timer),
+    };
+    let split_file = &mut timer.split_file;
+    split_file.record_segments(attempt.id, &attempt.splits);
+    split_file.attempt_history.push(attempt);
+}
+
+// Counts a reset during the segment currently being run
+fn record_reset(timer: &mut TimerState) {
+    let idx = timer.splits.len();
+    timer.split_file.resets[idx] += 1;
+}
+
+fn save_golds(timer: &mut TimerState) -> anyhow::Result<()> {
+    let run_summary = get_run_summary(timer);
+
+    for (i, seg) in run_summary.iter().enumerate() {
+        let file_golds = &mut timer.split_file.golds;
+        file_golds[i] = seg.gold.as_ref().map(|&g| Gold { duration: g });
+    }
+
+    write_split_file(&timer.split_file)?;
+
+    Ok(())
+}
+
+fn save_personal_best(timer: &mut TimerState) -> anyhow::Result<()> {
+    let splits = &timer.splits;
+    let curr_time = splits.last().unwrap().unwrap();
+
+    let should_save = match &timer.split_file.personal_best {
+        Some(pb) => {
+            let pb_time = pb.splits.last().unwrap().as_ref().unwrap().time;
+            curr_time < pb_time
+        }
+        None => true,
+    };
+
+    if should_save {
+        let pb = PersonalBest {
+            attempt: timer.split_file.attempts,
+            splits: run_splits(timer),
+        };
+        timer.split_file.personal_best = Some(pb);
+    }
+    write_split_file(&timer.split_file)?;
+
+    Ok(())
+}
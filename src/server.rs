@@ -0,0 +1,284 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+use crate::input::ActionEvent;
+use crate::settings::Action;
+use crate::timer_state::{TimerMode, TimerState};
+use crate::utils::{get_elapsed, get_run_summary};
+
+static OVERLAY_HTML: &str = include_str!("server/overlay.html");
+// How often each connection checks for new state to send, while waiting for commands
+static POLL_INTERVAL: Duration = Duration::from_millis(20);
+// While running, clients count the time up themselves, but are corrected this often
+static RESYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Clone)]
+pub struct ServerSettings {
+    pub port: u16,
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+struct ServerState {
+    title: String,
+    category: String,
+    mode: &'static str,
+    segments: Vec<SegmentState>,
+}
+
+// Times are in milliseconds. Live values are only given for completed splits.
+#[derive(Serialize, Clone, PartialEq)]
+struct SegmentState {
+    name: String,
+    split: Option<u64>,
+    segment: Option<u64>,
+    delta: Option<i64>,
+    pb_split: Option<u64>,
+    pb_segment: Option<u64>,
+    gold: Option<u64>,
+    is_gold_new: bool,
+}
+
+fn get_server_state(timer: &TimerState) -> ServerState {
+    let summary = get_run_summary(timer);
+    let ms = |dur: Option<Duration>| dur.map(|dur| dur.as_millis() as u64);
+
+    let segments = summary
+        .iter()
+        .enumerate()
+        .map(|(i, seg)| {
+            let done = i < timer.splits.len();
+            SegmentState {
+                name: timer.split_file.split_names[i].clone(),
+                split: ms(seg.live_split.filter(|_| done)),
+                segment: ms(seg.live_seg.filter(|_| done)),
+                delta: seg.live_delta.filter(|_| done).map(|delta| {
+                    let delta = delta.as_millis() as i64;
+                    if seg.live_delta_neg {
+                        -delta
+                    } else {
+                        delta
+                    }
+                }),
+                pb_split: ms(seg.pb_split),
+                pb_segment: ms(seg.pb_seg),
+                gold: ms(seg.gold),
+                is_gold_new: done && seg.is_gold_new,
+            }
+        })
+        .collect();
+
+    ServerState {
+        title: timer.split_file.title.clone(),
+        category: timer.split_file.category.clone(),
+        mode: match timer.mode {
+            TimerMode::Initial => "Initial",
+            TimerMode::Running { .. } => "Running",
+            TimerMode::Paused { .. } => "Paused",
+            TimerMode::Finished { .. } => "Finished",
+        },
+        segments,
+    }
+}
+
+// Every connected WebSocket client, and the last message sent so new clients can catch up
+#[derive(Default)]
+struct Clients {
+    senders: Vec<Sender<String>>,
+    latest: Option<String>,
+}
+
+/// Serves the browser overlay on localhost, pushing timer updates to it over a WebSocket and
+/// taking actions from it.
+pub struct Server {
+    clients: Arc<Mutex<Clients>>,
+    commands: Receiver<ActionEvent>,
+    prev_state: Option<ServerState>,
+    last_push: Instant,
+}
+
+impl Server {
+    pub fn start(settings: &ServerSettings) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port))
+            .with_context(|| format!("Failed to start server on port {}", settings.port))?;
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let (sender, commands) = mpsc::channel();
+
+        let port = settings.port;
+        let thread_clients = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = thread_clients.clone();
+                let sender = sender.clone();
+                // A connection that goes wrong only affects that client
+                thread::spawn(move || handle_connection(stream, port, clients, sender));
+            }
+        });
+
+        Ok(Self {
+            clients,
+            commands,
+            prev_state: None,
+            last_push: Instant::now(),
+        })
+    }
+
+    /// Actions sent by clients since the last call.
+    pub fn commands(&self) -> impl Iterator<Item = ActionEvent> + '_ {
+        self.commands.try_iter()
+    }
+
+    /// Sends the timer state to every client if it changed.
+    pub fn publish(&mut self, timer: &TimerState) {
+        let state = get_server_state(timer);
+        let running = matches!(timer.mode, TimerMode::Running { .. });
+        let resync = running && self.last_push.elapsed() >= RESYNC_INTERVAL;
+        if self.prev_state.as_ref() == Some(&state) && !resync {
+            return;
+        }
+
+        let message = json!({
+            "elapsed": get_elapsed(timer).as_millis() as u64,
+            "state": state,
+        })
+        .to_string();
+        let mut clients = self.clients.lock().unwrap();
+        clients
+            .senders
+            .retain(|sender| sender.send(message.clone()).is_ok());
+        clients.latest = Some(message);
+
+        self.prev_state = Some(state);
+        self.last_push = Instant::now();
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    port: u16,
+    clients: Arc<Mutex<Clients>>,
+    commands: Sender<ActionEvent>,
+) -> anyhow::Result<()> {
+    // Look at the request line without consuming it, since the WebSocket handshake reads it again
+    let mut buf = [0; 256];
+    let len = stream.peek(&mut buf)?;
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    match path {
+        "/ws" => handle_websocket(stream, port, clients, commands),
+        "/" => respond(stream, "200 OK", "text/html; charset=utf-8", OVERLAY_HTML),
+        _ => respond(stream, "404 Not Found", "text/plain", "Not found"),
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> anyhow::Result<()> {
+    // Read the rest of the request so the client doesn't see the connection reset
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..len]);
+    }
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())?;
+    Ok(())
+}
+
+// The handshake callback's error type is tungstenite's, however large it is
+#[allow(clippy::result_large_err)]
+fn handle_websocket(
+    stream: TcpStream,
+    port: u16,
+    clients: Arc<Mutex<Clients>>,
+    commands: Sender<ActionEvent>,
+) -> anyhow::Result<()> {
+    // Only pages served from here may connect, so other sites can't control the timer
+    let check_origin = move |request: &Request, response: Response| {
+        let allowed = [
+            format!("http://127.0.0.1:{}", port),
+            format!("http://localhost:{}", port),
+        ];
+        match request
+            .headers()
+            .get("Origin")
+            .map(|origin| origin.to_str())
+        {
+            None => Ok(response),
+            Some(Ok(origin)) if allowed.iter().any(|a| a == origin) => Ok(response),
+            _ => {
+                let mut error = ErrorResponse::new(Some("Origin not allowed".to_string()));
+                *error.status_mut() = StatusCode::FORBIDDEN;
+                Err(error)
+            }
+        }
+    };
+    let mut ws = tungstenite::accept_hdr(stream, check_origin)?;
+    ws.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (sender, updates) = mpsc::channel();
+    {
+        let mut clients = clients.lock().unwrap();
+        if let Some(latest) = &clients.latest {
+            sender.send(latest.clone())?;
+        }
+        clients.senders.push(sender);
+    }
+
+    loop {
+        match ws.read() {
+            Ok(Message::Text(text)) => handle_command(&mut ws, text.as_str(), &commands)?,
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        for update in updates.try_iter() {
+            ws.send(Message::text(update))?;
+        }
+    }
+}
+
+// Commands are action names as used for hotkeys, e.g. "Split"
+fn handle_command(
+    ws: &mut WebSocket<TcpStream>,
+    text: &str,
+    commands: &Sender<ActionEvent>,
+) -> anyhow::Result<()> {
+    let time = Instant::now();
+    match serde_json::from_value::<Action>(json!(text.trim())) {
+        Ok(action) => commands.send(ActionEvent { action, time })?,
+        Err(_) => {
+            let error = json!({ "error": format!("Unknown action: {}", text.trim()) });
+            ws.send(Message::text(error.to_string()))?;
+        }
+    }
+    Ok(())
+}
@@ -1,9 +1,20 @@
-use anyhow::anyhow;
-use std::{collections::HashMap, path::Path, str::FromStr, sync::LazyLock};
+use anyhow::{anyhow, Context};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::LazyLock,
+};
 
+use clap::ValueEnum;
 use device_query::Keycode;
 use serde::Deserialize;
 
+use crate::audio::AudioBackendName;
+use crate::events::EventKind;
+use crate::overlay::OverlaySettings;
+use crate::server::ServerSettings;
+
 pub struct Theme {
     pub bg: &'static str,
     pub normal_text: &'static str,
@@ -36,9 +47,20 @@ pub enum Action {
     ResetAndSave,
     ResetAndDelete,
     Pause,
+    SwitchSplitFile,
+    TogglePractice,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Delta,
+    Segment,
+    Split,
+    PossibleTimeSave,
+    Resets,
+}
+
+#[derive(Deserialize, ValueEnum, Clone, Copy)]
 pub enum ThemeName {
     Flitter,
 }
@@ -49,6 +71,22 @@ pub struct ParsedSettings {
     pub theme: ThemeName,
     pub global_hotkeys: HashMap<String, Action>,
     pub draw_background: bool,
+    pub columns: Vec<Column>,
+    pub fps: u32,
+    // Frame rate while nothing on screen is changing, if adaptive_fps is on
+    pub idle_fps: u32,
+    pub adaptive_fps: bool,
+    // Shell commands to run when timer events happen
+    pub hooks: HashMap<EventKind, String>,
+    // WAV files to play when timer events happen
+    pub sounds: HashMap<EventKind, PathBuf>,
+    pub audio_backend: AudioBackendName,
+    pub overlay: Option<OverlaySettings>,
+    pub server: Option<ServerSettings>,
+    // File that practice runs are appended to
+    pub practice_log: Option<PathBuf>,
+    // One set of hotkeys for each runner in a race, in the order their splits files are given
+    pub race_hotkeys: Vec<HashMap<String, Action>>,
 }
 
 impl Default for ParsedSettings {
@@ -64,6 +102,22 @@ impl Default for ParsedSettings {
                 ("Delete".to_string(), Action::ResetAndDelete),
             ]),
             draw_background: true,
+            columns: vec![
+                Column::Delta,
+                Column::Segment,
+                Column::Split,
+                Column::PossibleTimeSave,
+            ],
+            fps: 60,
+            idle_fps: 4,
+            adaptive_fps: true,
+            hooks: HashMap::new(),
+            sounds: HashMap::new(),
+            audio_backend: AudioBackendName::System,
+            overlay: None,
+            server: None,
+            practice_log: None,
+            race_hotkeys: Vec::new(),
         }
     }
 }
@@ -73,35 +127,94 @@ pub struct Settings {
     pub theme: &'static Theme,
     pub global_hotkeys: HashMap<Keycode, Action>,
     pub draw_background: bool,
+    pub columns: Vec<Column>,
+    pub fps: u32,
+    pub idle_fps: u32,
+    pub adaptive_fps: bool,
+    pub hooks: HashMap<EventKind, String>,
+    pub sounds: HashMap<EventKind, PathBuf>,
+    pub audio_backend: AudioBackendName,
+    pub overlay: Option<OverlaySettings>,
+    pub server: Option<ServerSettings>,
+    pub practice_log: Option<PathBuf>,
+    pub race_hotkeys: Vec<HashMap<Keycode, Action>>,
 }
 
 pub static DEFAULT_SETTINGS: LazyLock<Settings> =
     LazyLock::new(|| post_parse_settings(&ParsedSettings::default()).unwrap());
 
-fn post_parse_settings(parsed: &ParsedSettings) -> anyhow::Result<Settings> {
-    let theme = match parsed.theme {
+pub fn get_theme(name: ThemeName) -> &'static Theme {
+    match name {
         ThemeName::Flitter => &FLITTER_THEME,
-    };
+    }
+}
 
-    let mut global_hotkeys = HashMap::<Keycode, Action>::new();
-    for hotkey in parsed.global_hotkeys.iter() {
-        if let Ok(key) = Keycode::from_str(hotkey.0) {
-            global_hotkeys.insert(key, *hotkey.1);
-        } else {
-            return Err(anyhow!("Invalid hotkey: {}", &hotkey.0));
-        }
+fn post_parse_settings(parsed: &ParsedSettings) -> anyhow::Result<Settings> {
+    let theme = get_theme(parsed.theme);
+
+    let global_hotkeys = parse_hotkeys(&parsed.global_hotkeys)?;
+    let race_hotkeys = parsed
+        .race_hotkeys
+        .iter()
+        .map(parse_hotkeys)
+        .collect::<anyhow::Result<_>>()?;
+
+    if parsed.fps == 0 || parsed.idle_fps == 0 {
+        return Err(anyhow!("fps and idle_fps must be at least 1"));
     }
 
     Ok(Settings {
         theme,
         global_hotkeys,
         draw_background: parsed.draw_background,
+        columns: parsed.columns.clone(),
+        fps: parsed.fps,
+        idle_fps: parsed.idle_fps,
+        adaptive_fps: parsed.adaptive_fps,
+        hooks: parsed.hooks.clone(),
+        sounds: parsed.sounds.clone(),
+        audio_backend: parsed.audio_backend,
+        overlay: parsed.overlay.clone(),
+        server: parsed.server.clone(),
+        practice_log: parsed.practice_log.clone(),
+        race_hotkeys,
     })
 }
 
+fn parse_hotkeys(hotkeys: &HashMap<String, Action>) -> anyhow::Result<HashMap<Keycode, Action>> {
+    let mut parsed = HashMap::<Keycode, Action>::new();
+    for hotkey in hotkeys.iter() {
+        if let Ok(key) = Keycode::from_str(hotkey.0) {
+            parsed.insert(key, *hotkey.1);
+        } else {
+            return Err(anyhow!("Invalid hotkey: {}", &hotkey.0));
+        }
+    }
+    Ok(parsed)
+}
+
 pub fn read_settings_file(path: &Path) -> anyhow::Result<Settings> {
     let file = std::fs::File::open(path)?;
     let reader = std::io::BufReader::new(file);
     let parsed: ParsedSettings = serde_json::from_reader(reader)?;
     post_parse_settings(&parsed)
 }
+
+/// `$XDG_CONFIG_HOME/flitter-timer/config.json`, falling back to `$HOME/.config` if
+/// `XDG_CONFIG_HOME` isn't set. None if neither is set.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").filter(|h| !h.is_empty())?).join(".config"),
+    };
+    Some(config_dir.join("flitter-timer").join("config.json"))
+}
+
+/// Reads the settings file if there is one, falling back to the default settings.
+pub fn load_settings(path: &Path) -> anyhow::Result<Settings> {
+    if path.exists() {
+        read_settings_file(path).context("Failed to read settings file")
+    } else {
+        Ok(DEFAULT_SETTINGS.clone())
+    }
+}
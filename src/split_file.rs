@@ -3,10 +3,24 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use crate::utils::{format_duration, Prefix, Sign};
+use crate::autosplitter::AutosplitterConfig;
+
+mod check;
+mod migrations;
+
+pub use check::Fix;
+
+/// Version of the splits file format written by this flitter.
+pub static SPLIT_FILE_VERSION: u32 = 4;
+
+/// Attempt that segment practice times are recorded under in the segment history, since they
+/// aren't from any attempt. Real attempts count from 1.
+pub static PRACTICE_ATTEMPT: u32 = 0;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SplitFile {
+    #[serde(default)]
+    pub version: u32,
     pub title: String,
     pub category: String,
     #[serde(default)]
@@ -18,38 +32,383 @@ pub struct SplitFile {
     pub golds: Vec<Option<Gold>>,
     #[serde(default)]
     pub personal_best: Option<PersonalBest>,
+    #[serde(default)]
+    pub attempt_history: Vec<Attempt>,
+    // How many runs were reset during each segment
+    #[serde(default)]
+    pub resets: Vec<u32>,
+    // Completed runs from before resets were counted, which reset rates leave out. Unknown for
+    // files that counted resets before this was recorded.
+    #[serde(default)]
+    pub completed_before_resets: Option<u32>,
+    // Every completed time of each segment, oldest first
+    #[serde(default)]
+    pub segment_history: Vec<Vec<SegmentTime>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autosplitter: Option<AutosplitterConfig>,
 
     #[serde(skip)]
     file_path: PathBuf,
 }
 
+impl SplitFile {
+    pub fn new(path: &Path, title: String, category: String, split_names: Vec<String>) -> Self {
+        let split_count = split_names.len();
+        Self {
+            version: SPLIT_FILE_VERSION,
+            title,
+            category,
+            attempts: 0,
+            completed: 0,
+            split_names,
+            golds: vec![None; split_count],
+            personal_best: None,
+            attempt_history: Vec::new(),
+            resets: vec![0; split_count],
+            completed_before_resets: Some(0),
+            segment_history: vec![Vec::new(); split_count],
+            autosplitter: None,
+            file_path: path.to_owned(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Number of runs that made it to the given segment, either resetting during it or later,
+    /// or completing the run. Only runs since resets were counted are included, and `None` if
+    /// it isn't known which those are.
+    pub fn runs_reaching(&self, idx: usize) -> Option<u32> {
+        let completed = self.completed.saturating_sub(self.completed_before_resets?);
+        Some(self.resets[idx..].iter().sum::<u32>() + completed)
+    }
+
+    pub fn segment_times(&self, idx: usize) -> impl Iterator<Item = Duration> + '_ {
+        self.segment_history[idx].iter().map(|seg| seg.duration)
+    }
+
+    /// Best time of a segment among the last `count` attempts.
+    pub fn best_recent_segment(&self, idx: usize, count: u32) -> Option<Duration> {
+        let first_attempt = self.attempts.saturating_sub(count);
+        self.segment_history[idx]
+            .iter()
+            .filter(|seg| seg.attempt > first_attempt)
+            .map(|seg| seg.duration)
+            .min()
+    }
+
+    /// Adds the completed segments of a run to the segment history.
+    pub fn record_segments(&mut self, attempt: u32, splits: &[Option<Split>]) {
+        push_segments(&mut self.segment_history, attempt, splits);
+    }
+
+    /// Adds the segments completed in segment practice, from split `first` on, to the segment
+    /// history.
+    pub fn record_practice_segments(&mut self, first: usize, splits: &[Option<Split>]) {
+        for (i, segments) in self.segment_history.iter_mut().enumerate().skip(first) {
+            if let Some(duration) = get_segment(splits, i) {
+                segments.push(SegmentTime {
+                    attempt: PRACTICE_ATTEMPT,
+                    duration,
+                });
+            }
+        }
+    }
+
+    /// Rebuilds every gold from the fastest time in the PB, attempt history and segment history.
+    /// Segments with no recorded time keep their gold, since there's nothing to rebuild it from,
+    /// and are listed.
+    pub fn recompute_golds(&mut self) -> GoldRecompute {
+        let mut result = GoldRecompute::default();
+        for i in 0..self.split_names.len() {
+            let old = self.golds[i].as_ref().map(|g| g.duration);
+            let Some(new) = self.best_recorded_segment(i) else {
+                if old.is_some() {
+                    result.unrecorded.push(i);
+                }
+                continue;
+            };
+
+            if old != Some(new) {
+                result.changes.push(GoldChange {
+                    idx: i,
+                    old,
+                    new: Some(new),
+                });
+                self.golds[i] = Some(Gold { duration: new });
+            }
+        }
+        result
+    }
+
+    // Fastest time of a segment in the PB, attempt history and segment history
+    fn best_recorded_segment(&self, idx: usize) -> Option<Duration> {
+        let pb = self.personal_best.as_ref();
+        let pb_seg = pb.and_then(|pb| get_segment(&pb.splits, idx));
+        let attempt_segs = self
+            .attempt_history
+            .iter()
+            .flat_map(|attempt| get_segment(&attempt.splits, idx));
+        self.segment_times(idx)
+            .chain(attempt_segs)
+            .chain(pb_seg)
+            .min()
+    }
+
+    pub fn rename_split(&mut self, idx: usize, name: String) {
+        self.split_names[idx] = name;
+    }
+
+    /// Inserts a split before `idx` with no gold, history or PB time. The following segment also
+    /// loses its gold and history, since those were measured across both segments.
+    pub fn insert_split(&mut self, idx: usize, name: String) {
+        let prev_len = self.split_names.len();
+        self.split_names.insert(idx, name);
+        self.golds.insert(idx, None);
+        self.resets.insert(idx, 0);
+        self.segment_history.insert(idx, Vec::new());
+        if idx + 1 < self.split_names.len() {
+            self.golds[idx + 1] = None;
+            self.segment_history[idx + 1].clear();
+        }
+        if let Some(patterns) = self.autosplitter_patterns_mut() {
+            patterns.insert(idx, None);
+        }
+
+        if let Some(pb) = &mut self.personal_best {
+            pb.splits.insert(idx, None);
+        }
+        for attempt in self.attempt_history.iter_mut() {
+            // Completed attempts stay completed when a split is added at the end
+            if attempt.splits.len() > idx || attempt.splits.len() == prev_len {
+                attempt.splits.insert(idx, None);
+            }
+        }
+    }
+
+    /// Removes a split. Its segment is folded into the next one, or dropped if it was the last
+    /// segment of the run.
+    pub fn remove_split(&mut self, idx: usize) -> anyhow::Result<()> {
+        if self.split_names.len() == 1 {
+            return Err(anyhow!("Cannot remove the only split"));
+        }
+
+        if idx + 1 < self.split_names.len() {
+            let name = self.split_names[idx + 1].clone();
+            self.merge_segments(idx, name);
+            return Ok(());
+        }
+
+        self.split_names.pop();
+        self.golds.pop();
+        self.resets.pop();
+        self.segment_history.pop();
+        if let Some(patterns) = self.autosplitter_patterns_mut() {
+            patterns.pop();
+        }
+        if let Some(pb) = &mut self.personal_best {
+            pb.splits.pop();
+        }
+        for attempt in self.attempt_history.iter_mut() {
+            attempt.splits.truncate(idx);
+        }
+        Ok(())
+    }
+
+    /// Merges segment `idx` with the one after it. Times of the merged segment are the sums of
+    /// both segments' times from the same attempt.
+    pub fn merge_segments(&mut self, idx: usize, name: String) {
+        self.split_names.remove(idx);
+        self.split_names[idx] = name;
+
+        let first_resets = self.resets.remove(idx);
+        self.resets[idx] += first_resets;
+        // The merged segment ends where the second one did, so it keeps its pattern
+        if let Some(patterns) = self.autosplitter_patterns_mut() {
+            patterns.remove(idx);
+        }
+
+        // The split between the two segments disappears, the cumulative times stay the same
+        if let Some(pb) = &mut self.personal_best {
+            pb.splits.remove(idx);
+        }
+        for attempt in self.attempt_history.iter_mut() {
+            if attempt.splits.len() > idx {
+                attempt.splits.remove(idx);
+            }
+        }
+
+        let first = self.segment_history.remove(idx);
+        let second = &mut self.segment_history[idx];
+        *second = second
+            .iter()
+            .filter_map(|seg| {
+                // Practice times of the two segments weren't necessarily done back to back
+                if seg.attempt == PRACTICE_ATTEMPT {
+                    return None;
+                }
+                let other = first.iter().find(|s| s.attempt == seg.attempt)?;
+                Some(SegmentTime {
+                    attempt: seg.attempt,
+                    duration: other.duration + seg.duration,
+                })
+            })
+            .collect();
+
+        let first_gold = self.golds.remove(idx);
+        let pb_seg = self
+            .personal_best
+            .as_ref()
+            .and_then(|pb| get_segment(&pb.splits, idx));
+        let best = self.segment_times(idx).chain(pb_seg).min();
+        // Without any recorded times of the merged segment, the sum of both golds is the best
+        // estimate
+        let summed = match (&first_gold, &self.golds[idx]) {
+            (Some(g1), Some(g2)) => Some(g1.duration + g2.duration),
+            _ => None,
+        };
+        self.golds[idx] = best.or(summed).map(|duration| Gold { duration });
+    }
+
+    /// Swaps segment `idx` with the one after it, so the run visits them in the opposite order.
+    pub fn swap_segments(&mut self, idx: usize) {
+        self.split_names.swap(idx, idx + 1);
+        self.golds.swap(idx, idx + 1);
+        self.resets.swap(idx, idx + 1);
+        self.segment_history.swap(idx, idx + 1);
+        if let Some(patterns) = self.autosplitter_patterns_mut() {
+            patterns.swap(idx, idx + 1);
+        }
+
+        // Only the split between the two segments moves
+        let swap_splits = |splits: &mut Vec<Option<Split>>| {
+            if splits.len() <= idx {
+                return;
+            }
+            let second_seg = get_segment(splits, idx + 1);
+            let prev_split = if idx == 0 {
+                Some(Duration::from_secs(0))
+            } else {
+                splits[idx - 1].as_ref().map(|s| s.time)
+            };
+            splits[idx] = match (prev_split, second_seg) {
+                (Some(prev_split), Some(seg)) => Some(Split {
+                    time: prev_split + seg,
+                }),
+                _ => None,
+            };
+        };
+        if let Some(pb) = &mut self.personal_best {
+            swap_splits(&mut pb.splits);
+        }
+        for attempt in self.attempt_history.iter_mut() {
+            swap_splits(&mut attempt.splits);
+        }
+    }
+
+    fn autosplitter_patterns_mut(&mut self) -> Option<&mut Vec<Option<String>>> {
+        self.autosplitter
+            .as_mut()
+            .and_then(|autosplitter| autosplitter.split_patterns_mut())
+    }
+
+    /// Checks that all per-split data lines up and that the PB and golds are consistent.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self.problems().first() {
+            Some(problem) => Err(anyhow!("{}", problem.message)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn push_segments(history: &mut [Vec<SegmentTime>], attempt: u32, splits: &[Option<Split>]) {
+    for (i, segments) in history.iter_mut().enumerate() {
+        if let Some(duration) = get_segment(splits, i) {
+            segments.push(SegmentTime { attempt, duration });
+        }
+    }
+}
+
+/// Duration of a segment given a run's splits, if both ends of the segment are known.
+pub fn get_segment(splits: &[Option<Split>], idx: usize) -> Option<Duration> {
+    let split = splits.get(idx)?.as_ref()?.time;
+    if idx == 0 {
+        Some(split)
+    } else {
+        let prev_split = splits[idx - 1].as_ref()?.time;
+        Some(split.saturating_sub(prev_split))
+    }
+}
+
+pub struct GoldChange {
+    pub idx: usize,
+    pub old: Option<Duration>,
+    pub new: Option<Duration>,
+}
+
+#[derive(Default)]
+pub struct GoldRecompute {
+    pub changes: Vec<GoldChange>,
+    // Segments that kept their gold because they have no recorded time
+    pub unrecorded: Vec<usize>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Gold {
     #[serde(with = "duration_format")]
     pub duration: Duration,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PersonalBest {
     pub attempt: u32,
     pub splits: Vec<Option<Split>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Split {
     #[serde(with = "duration_format")]
     pub time: Duration,
 }
 
+/// A saved attempt. Runs that were reset early have fewer splits than there are split names.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attempt {
+    pub id: u32,
+    pub splits: Vec<Option<Split>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SegmentTime {
+    pub attempt: u32,
+    #[serde(with = "duration_format")]
+    pub duration: Duration,
+}
+
+pub(crate) mod option_duration_format {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match duration {
+            Some(duration) => super::duration_format::serialize(duration, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
 mod duration_format {
     use core::fmt;
-    use std::str::FromStr;
-    use std::{sync::LazyLock, time::Duration};
+    use std::time::Duration;
 
-    use regex::{Match, Regex};
     use serde::{de, Deserializer, Serializer};
 
-    use crate::utils::{format_duration, Prefix, Sign};
+    use crate::utils::{format_duration, parse_duration, Prefix, Sign};
 
     pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -63,10 +422,6 @@ mod duration_format {
         ))
     }
 
-    static DURATION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(r"^(?:(?:(?:(\d+):)?(\d+):)?(\d+):)?(\d+)(?:\.(\d{1,3}))?$").unwrap()
-    });
-
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
     where
         D: Deserializer<'de>,
@@ -84,125 +439,62 @@ mod duration_format {
             where
                 E: de::Error,
             {
-                let caps = DURATION_REGEX
-                    .captures(value)
-                    .ok_or_else(|| E::custom("invalid duration format"))?;
-
-                let days = get_or_zero(&caps.get(1))?;
-                let hours = get_or_zero(&caps.get(2))?;
-                let minutes = get_or_zero(&caps.get(3))?;
-                let seconds = get_or_zero(&caps.get(4))?;
-                let milliseconds = get_or_zero_padded(&caps.get(5))?;
-
-                let secs = seconds + (minutes * 60) + (hours * 60 * 60) + (days * 60 * 60 * 24);
-                let dur = Duration::from_secs(secs) + Duration::from_millis(milliseconds);
-                Ok(dur)
+                parse_duration(value).ok_or_else(|| E::custom("invalid duration format"))
             }
         }
 
         deserializer.deserialize_str(DurationVisitor)
     }
-
-    fn get_or_zero<E>(mat: &Option<Match<'_>>) -> Result<u64, E>
-    where
-        E: de::Error,
-    {
-        let num = match mat {
-            Some(d) => u64::from_str(d.as_str()).map_err(E::custom)?,
-            None => 0,
-        };
-        Ok(num)
-    }
-
-    fn get_or_zero_padded<E>(mat: &Option<Match<'_>>) -> Result<u64, E>
-    where
-        E: de::Error,
-    {
-        let num = match mat {
-            Some(d) => {
-                let mut num_str = d.as_str().to_string();
-                while num_str.len() < 3 {
-                    num_str.push('0');
-                }
-                u64::from_str(&num_str).unwrap()
-            }
-            None => 0,
-        };
-        Ok(num)
-    }
 }
 
 pub fn read_split_file(path: &Path) -> anyhow::Result<SplitFile> {
+    let split_file = read_split_file_unchecked(path)?;
+    split_file.validate()?;
+    Ok(split_file)
+}
+
+/// Reads a splits file without validating it, so its problems can be inspected and repaired.
+pub fn read_split_file_unchecked(path: &Path) -> anyhow::Result<SplitFile> {
     let file = std::fs::File::open(path)?;
     let reader = std::io::BufReader::new(file);
-    let mut split_file: SplitFile = serde_json::from_reader(reader)?;
+    let mut value: serde_json::Value = serde_json::from_reader(reader)?;
+    migrations::migrate(&mut value)?;
+    let mut split_file: SplitFile = serde_json::from_value(value)?;
     split_file.file_path = path.to_owned();
 
-    if split_file.split_names.is_empty() {
-        return Err(anyhow!("Split names cannot be empty"));
-    }
-
     if split_file.golds.is_empty() {
         split_file.golds = vec![None; split_file.split_names.len()];
     }
 
-    if split_file.golds.len() != split_file.split_names.len() {
-        return Err(anyhow!(
-            "Split name count ({}) does not match gold count ({})",
-            split_file.split_names.len(),
-            split_file.golds.len()
-        ));
+    if split_file.resets.is_empty() {
+        split_file.resets = vec![0; split_file.split_names.len()];
     }
-    
-    if let Some(pb) = &split_file.personal_best {
-        if pb.splits.len() != split_file.split_names.len() {
-            return Err(anyhow!(
-                "Split name count ({}) does not match personal best split count ({})",
-                split_file.split_names.len(),
-                pb.splits.len(),
-            ));
-        }
-
-        if pb.splits.last().unwrap().is_none() {
-            return Err(anyhow!("Last split of personal best cannot be null"));
-        }
 
-        for i in 0..split_file.split_names.len() {
-            let pb_split = pb.splits[i].as_ref();
-            let gold = split_file.golds[i].as_ref();
-            let prev_pb_split = if i == 0 {
-                None
-            } else {
-                pb.splits[i - 1].as_ref()
-            };
-
-            if let (Some(pb_split), Some(prev_pb_split)) = (pb_split, prev_pb_split) {
-                if pb_split.time < prev_pb_split.time {
-                    return Err(anyhow!(
-                        "Split {} ({}) is earlier than previous split",
-                        i + 1,
-                        format_duration(pb_split.time, 3, Sign::Positive, Prefix::NoneOrMinus)
-                    ));
-                }
-                if let Some(gold) = gold {
-                    let pb_dur = pb_split.time - prev_pb_split.time;
-                    if gold.duration > pb_dur {
-                        return Err(anyhow!(
-                            "Gold {} ({}) is longer than the PB segment",
-                            i + 1,
-                            format_duration(gold.duration, 3, Sign::Positive, Prefix::NoneOrMinus)
-                        ));
-                    }
-                }
-            }
-        }
-    } 
+    if split_file.segment_history.is_empty() {
+        split_file.segment_history = vec![Vec::new(); split_file.split_names.len()];
+    }
 
     Ok(split_file)
 }
 
-pub fn write_split_file(split_file: &SplitFile) -> anyhow::Result<()> {
-    let file = std::fs::File::create(&split_file.file_path)?;
+/// Writes a new splits file, failing if the file already exists.
+pub fn create_split_file(split_file: &SplitFile) -> anyhow::Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&split_file.file_path)?;
     serde_json::to_writer_pretty(file, split_file)?;
     Ok(())
 }
+
+pub fn write_split_file(split_file: &SplitFile) -> anyhow::Result<()> {
+    // Write to a temporary file first so a crash mid-write can't corrupt the splits
+    let mut tmp_path = split_file.file_path.clone().into_os_string();
+    tmp_path.push(".tmp");
+    let file = std::fs::File::create(&tmp_path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, split_file)?;
+    writer.into_inner()?.sync_all()?;
+    std::fs::rename(&tmp_path, &split_file.file_path)?;
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use super::{Gold, SplitFile};
+use crate::utils::{format_duration, Prefix, Sign};
+
+/// A repair for a problem in a splits file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fix {
+    ResizeGolds,
+    ResizeResets,
+    ResizeSegmentHistory,
+    ResizeAutosplitterSplits,
+    DropPersonalBest,
+    GoldFromPersonalBest(usize),
+}
+
+impl Fix {
+    pub fn description(&self) -> String {
+        match self {
+            Fix::ResizeGolds => "Pad or truncate golds to the split count".to_string(),
+            Fix::ResizeResets => "Pad or truncate reset counts to the split count".to_string(),
+            Fix::ResizeSegmentHistory => {
+                "Pad or truncate segment history to the split count".to_string()
+            }
+            Fix::ResizeAutosplitterSplits => {
+                "Pad or truncate autosplitter split patterns to the split count".to_string()
+            }
+            Fix::DropPersonalBest => "Remove the personal best".to_string(),
+            Fix::GoldFromPersonalBest(i) => {
+                format!("Set gold {} to the PB segment time", i + 1)
+            }
+        }
+    }
+}
+
+pub struct Problem {
+    // Where in the file the problem is, e.g. "golds[3]"
+    pub location: String,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+impl Problem {
+    fn new(location: &str, message: String, fix: Option<Fix>) -> Self {
+        Self {
+            location: location.to_string(),
+            message,
+            fix,
+        }
+    }
+}
+
+fn fmt_dur(dur: Duration) -> String {
+    format_duration(dur, 3, Sign::Positive, Prefix::NoneOrMinus)
+}
+
+impl SplitFile {
+    /// Finds every problem that would stop this file from being loaded.
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let split_count = self.split_names.len();
+
+        if split_count == 0 {
+            problems.push(Problem::new(
+                "split_names",
+                "Split names cannot be empty".to_string(),
+                None,
+            ));
+        }
+
+        if self.golds.len() != split_count {
+            problems.push(Problem::new(
+                "golds",
+                format!(
+                    "Split name count ({}) does not match gold count ({})",
+                    split_count,
+                    self.golds.len()
+                ),
+                Some(Fix::ResizeGolds),
+            ));
+        }
+
+        if self.resets.len() != split_count {
+            problems.push(Problem::new(
+                "resets",
+                format!(
+                    "Split name count ({}) does not match reset count ({})",
+                    split_count,
+                    self.resets.len()
+                ),
+                Some(Fix::ResizeResets),
+            ));
+        }
+
+        if self.segment_history.len() != split_count {
+            problems.push(Problem::new(
+                "segment_history",
+                format!(
+                    "Split name count ({}) does not match segment history count ({})",
+                    split_count,
+                    self.segment_history.len()
+                ),
+                Some(Fix::ResizeSegmentHistory),
+            ));
+        }
+
+        if let Some(patterns) = self.autosplitter.as_ref().and_then(|a| a.split_patterns())
+            && patterns.len() != split_count
+        {
+            problems.push(Problem::new(
+                "autosplitter.splits",
+                format!(
+                    "Split name count ({}) does not match autosplitter split pattern count ({})",
+                    split_count,
+                    patterns.len()
+                ),
+                Some(Fix::ResizeAutosplitterSplits),
+            ));
+        }
+
+        let Some(pb) = &self.personal_best else {
+            return problems;
+        };
+
+        if pb.splits.len() != split_count {
+            problems.push(Problem::new(
+                "personal_best.splits",
+                format!(
+                    "Split name count ({}) does not match personal best split count ({})",
+                    split_count,
+                    pb.splits.len(),
+                ),
+                Some(Fix::DropPersonalBest),
+            ));
+            // The remaining checks compare splits by index
+            return problems;
+        }
+
+        if let Some(None) = pb.splits.last() {
+            problems.push(Problem::new(
+                &format!("personal_best.splits[{}]", split_count - 1),
+                "Last split of personal best cannot be null".to_string(),
+                Some(Fix::DropPersonalBest),
+            ));
+        }
+
+        for i in 1..split_count {
+            let (Some(pb_split), Some(prev_pb_split)) = (&pb.splits[i], &pb.splits[i - 1]) else {
+                continue;
+            };
+
+            if pb_split.time < prev_pb_split.time {
+                problems.push(Problem::new(
+                    &format!("personal_best.splits[{}]", i),
+                    format!(
+                        "Split {} ({}) is earlier than previous split",
+                        i + 1,
+                        fmt_dur(pb_split.time)
+                    ),
+                    Some(Fix::DropPersonalBest),
+                ));
+            } else if let Some(Some(gold)) = self.golds.get(i) {
+                let pb_dur = pb_split.time - prev_pb_split.time;
+                if gold.duration > pb_dur {
+                    problems.push(Problem::new(
+                        &format!("golds[{}]", i),
+                        format!(
+                            "Gold {} ({}) is longer than the PB segment ({})",
+                            i + 1,
+                            fmt_dur(gold.duration),
+                            fmt_dur(pb_dur)
+                        ),
+                        Some(Fix::GoldFromPersonalBest(i)),
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    pub fn apply_fix(&mut self, fix: Fix) {
+        let split_count = self.split_names.len();
+        match fix {
+            Fix::ResizeGolds => self.golds.resize(split_count, None),
+            Fix::ResizeResets => self.resets.resize(split_count, 0),
+            Fix::ResizeSegmentHistory => self.segment_history.resize(split_count, Vec::new()),
+            Fix::ResizeAutosplitterSplits => {
+                if let Some(patterns) = self
+                    .autosplitter
+                    .as_mut()
+                    .and_then(|a| a.split_patterns_mut())
+                {
+                    patterns.resize(split_count, None);
+                }
+            }
+            Fix::DropPersonalBest => self.personal_best = None,
+            Fix::GoldFromPersonalBest(i) => {
+                let pb = self.personal_best.as_ref().unwrap();
+                let duration = super::get_segment(&pb.splits, i).unwrap();
+                self.golds[i] = Some(Gold { duration });
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{anyhow, Context};
+use serde_json::{Map, Value};
+
+use super::{push_segments, Attempt, PersonalBest, SPLIT_FILE_VERSION};
+
+type Migration = fn(&mut Map<String, Value>) -> anyhow::Result<()>;
+
+// Entry `i` upgrades a splits file from version `i + 1` to version `i + 2`
+static MIGRATIONS: &[Migration] = &[v1_to_v2, v2_to_v3, v3_to_v4];
+
+/// Upgrades a parsed splits file to the current version, one version at a time.
+pub fn migrate(value: &mut Value) -> anyhow::Result<()> {
+    let obj = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Splits file must be a JSON object"))?;
+
+    // Files written before versioning was introduced have no version field
+    let version = match obj.get("version") {
+        Some(version) => version
+            .as_u64()
+            .filter(|&v| v >= 1)
+            .ok_or_else(|| anyhow!("Invalid splits file version: {}", version))?
+            as u32,
+        None => 1,
+    };
+
+    if version > SPLIT_FILE_VERSION {
+        return Err(anyhow!(
+            "Splits file version {} is newer than the latest version this flitter supports ({}), please upgrade flitter",
+            version,
+            SPLIT_FILE_VERSION
+        ));
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        migration(obj)
+            .with_context(|| format!("Failed to upgrade splits file to version {}", i + 2))?;
+    }
+    obj.insert("version".to_string(), SPLIT_FILE_VERSION.into());
+
+    Ok(())
+}
+
+// Version 2 added attempt history, per-segment reset counts and segment history
+fn v1_to_v2(obj: &mut Map<String, Value>) -> anyhow::Result<()> {
+    let split_count = obj
+        .get("split_names")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len);
+
+    let attempt_history: Vec<Attempt> = match obj.get("attempt_history") {
+        Some(history) => serde_json::from_value(history.clone())?,
+        None => Vec::new(),
+    };
+    let personal_best: Option<PersonalBest> = match obj.get("personal_best") {
+        Some(pb) => serde_json::from_value(pb.clone())?,
+        None => None,
+    };
+
+    if !obj.contains_key("segment_history") {
+        let mut history = vec![Vec::new(); split_count];
+        for attempt in &attempt_history {
+            push_segments(&mut history, attempt.id, &attempt.splits);
+        }
+        if let Some(pb) = &personal_best
+            && !attempt_history.iter().any(|a| a.id == pb.attempt)
+        {
+            push_segments(&mut history, pb.attempt, &pb.splits);
+        }
+        for segments in history.iter_mut() {
+            segments.sort_by_key(|seg| seg.attempt);
+        }
+        obj.insert(
+            "segment_history".to_string(),
+            serde_json::to_value(history)?,
+        );
+    }
+
+    obj.entry("attempt_history")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    obj.entry("resets")
+        .or_insert_with(|| vec![0; split_count].into());
+    // Resets are only counted from here on, so earlier completed runs can't be in reset rates
+    let completed = obj.get("completed").cloned().unwrap_or(0.into());
+    obj.entry("completed_before_resets").or_insert(completed);
+
+    Ok(())
+}
+
+// Version 3 added the optional autosplitter. There's nothing to upgrade, but older versions
+// would drop it when saving.
+fn v2_to_v3(_: &mut Map<String, Value>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+// Version 4 added the number of completed runs from before resets were counted. For files that
+// were upgraded to version 2 before then, it's only known if no resets were ever counted.
+fn v3_to_v4(obj: &mut Map<String, Value>) -> anyhow::Result<()> {
+    if obj.contains_key("completed_before_resets") {
+        return Ok(());
+    }
+    let no_resets = obj
+        .get("resets")
+        .and_then(Value::as_array)
+        .is_none_or(|resets| resets.iter().all(|count| count.as_u64() == Some(0)));
+    let completed = if no_resets {
+        obj.get("completed").cloned().unwrap_or(0.into())
+    } else {
+        Value::Null
+    };
+    obj.insert("completed_before_resets".to_string(), completed);
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::split_file::{get_segment, option_duration_format, SplitFile};
+use crate::utils::{format_duration, Prefix, Sign};
+
+// How many of the latest attempts `best_recent` looks at
+pub static RECENT_ATTEMPTS: u32 = 10;
+
+#[derive(Serialize)]
+pub struct SegmentStats {
+    pub name: String,
+    #[serde(serialize_with = "option_duration_format::serialize")]
+    pub gold: Option<Duration>,
+    #[serde(serialize_with = "option_duration_format::serialize")]
+    pub pb_segment: Option<Duration>,
+    #[serde(serialize_with = "option_duration_format::serialize")]
+    pub average: Option<Duration>,
+    #[serde(serialize_with = "option_duration_format::serialize")]
+    pub median: Option<Duration>,
+    #[serde(serialize_with = "option_duration_format::serialize")]
+    pub std_dev: Option<Duration>,
+    #[serde(serialize_with = "option_duration_format::serialize")]
+    pub best_recent: Option<Duration>,
+
+    // Number of recorded segment times the above are computed from
+    pub samples: usize,
+
+    // Runs that started this segment, and how many of those reset during it. Runs from before
+    // resets were counted aren't included, and `reached` is unknown if they can't be told apart.
+    pub reached: Option<u32>,
+    pub resets: u32,
+    pub reset_rate: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct RunStats {
+    pub title: String,
+    pub category: String,
+    pub attempts: u32,
+    pub completed: u32,
+    pub completion_rate: Option<f64>,
+    pub segments: Vec<SegmentStats>,
+}
+
+pub fn get_run_stats(split_file: &SplitFile) -> RunStats {
+    let segments = (0..split_file.split_names.len())
+        .map(|i| {
+            let mut times: Vec<Duration> = split_file.segment_times(i).collect();
+            times.sort();
+
+            let reached = split_file.runs_reaching(i);
+            let resets = split_file.resets[i];
+
+            let pb_segment =
+                (split_file.personal_best.as_ref()).and_then(|pb| get_segment(&pb.splits, i));
+
+            SegmentStats {
+                name: split_file.split_names[i].clone(),
+                gold: split_file.golds[i].as_ref().map(|g| g.duration),
+                pb_segment,
+                average: get_average(&times),
+                median: get_median(&times),
+                std_dev: get_std_dev(&times),
+                best_recent: split_file.best_recent_segment(i, RECENT_ATTEMPTS),
+                samples: times.len(),
+                reached,
+                resets,
+                reset_rate: reached.and_then(|reached| get_rate(resets, reached)),
+            }
+        })
+        .collect();
+
+    RunStats {
+        title: split_file.title.clone(),
+        category: split_file.category.clone(),
+        attempts: split_file.attempts,
+        completed: split_file.completed,
+        completion_rate: get_rate(split_file.completed, split_file.attempts),
+        segments,
+    }
+}
+
+fn get_rate(count: u32, total: u32) -> Option<f64> {
+    if total == 0 {
+        None
+    } else {
+        Some(count as f64 / total as f64)
+    }
+}
+
+fn get_average(sorted: &[Duration]) -> Option<Duration> {
+    if sorted.is_empty() {
+        None
+    } else {
+        Some(sorted.iter().sum::<Duration>() / sorted.len() as u32)
+    }
+}
+
+fn get_median(sorted: &[Duration]) -> Option<Duration> {
+    if sorted.is_empty() {
+        None
+    } else if sorted.len().is_multiple_of(2) {
+        let mid = sorted.len() / 2;
+        Some((sorted[mid - 1] + sorted[mid]) / 2)
+    } else {
+        Some(sorted[sorted.len() / 2])
+    }
+}
+
+fn get_std_dev(sorted: &[Duration]) -> Option<Duration> {
+    let average = get_average(sorted)?.as_secs_f64();
+    let variance = sorted
+        .iter()
+        .map(|d| (d.as_secs_f64() - average).powi(2))
+        .sum::<f64>()
+        / sorted.len() as f64;
+    Some(Duration::from_secs_f64(variance.sqrt()))
+}
+
+pub fn format_run_stats(stats: &RunStats) -> String {
+    let fmt_dur = |dur: Option<Duration>| match dur {
+        Some(dur) => format_duration(dur, 2, Sign::Positive, Prefix::NoneOrMinus),
+        None => "-".to_string(),
+    };
+    let fmt_rate = |rate: Option<f64>| match rate {
+        Some(rate) => format!("{:.1}%", rate * 100.0),
+        None => "-".to_string(),
+    };
+
+    let mut out = format!("{}\n{}\n\n", stats.title, stats.category);
+    out += &format!(
+        "Attempts: {}  Completed: {}  Completion rate: {}\n\n",
+        stats.attempts,
+        stats.completed,
+        fmt_rate(stats.completion_rate)
+    );
+
+    out += &format!(
+        "{:<16}{:>12}{:>12}{:>12}{:>12}{:>12}{:>12}{:>8}{:>12}\n",
+        "",
+        "Gold",
+        "PB Segment",
+        "Average",
+        "Median",
+        "Std Dev",
+        format!("Best of {}", RECENT_ATTEMPTS),
+        "Resets",
+        "Reset Rate"
+    );
+    for seg in &stats.segments {
+        out += &format!(
+            "{:<16}{:>12}{:>12}{:>12}{:>12}{:>12}{:>12}{:>8}{:>12}\n",
+            seg.name,
+            fmt_dur(seg.gold),
+            fmt_dur(seg.pb_segment),
+            fmt_dur(seg.average),
+            fmt_dur(seg.median),
+            fmt_dur(seg.std_dev),
+            fmt_dur(seg.best_recent),
+            seg.resets,
+            fmt_rate(seg.reset_rate),
+        );
+    }
+    out
+}
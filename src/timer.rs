@@ -1,65 +1,258 @@
-use std::collections::HashSet;
+use std::cell::Cell;
 use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
 use crossterm::style::Color;
-use device_query::{DeviceQuery, DeviceState, Keycode};
 
-use crate::settings::{self, Action, Settings};
-use crate::split_file::{write_split_file, Gold, PersonalBest, Split};
+use crate::audio::{self, SoundCues};
+use crate::autosplitter::{load_autosplitter, Autosplitter, AutosplitterCommand, TICK_INTERVAL};
+use crate::events::{split_events, EventBus, EventListener, TimerEvent};
+use crate::hooks::Hooks;
+use crate::input::{self, ActionEvent};
+use crate::overlay::Overlay;
+use crate::picker::{Picker, PickerChoice};
+use crate::practice::PracticeLog;
+use crate::saving::RunSaver;
+use crate::server::Server;
+use crate::settings::{Action, Settings};
+use crate::split_file::SplitFile;
 use crate::timer_state::{TimerMode, TimerState};
-use crate::utils::{get_run_summary, parse_color};
+use crate::utils::parse_color;
 use crate::{rotty::Renderer, split_file::read_split_file, view};
 
 pub struct Timer {
-    device_state: DeviceState,
+    hotkeys: Receiver<ActionEvent>,
     renderer: Renderer,
     timer_state: TimerState,
     settings: Settings,
-    prev_keys: HashSet<Keycode>,
+    // Shown instead of the timer while choosing another splits file
+    picker: Option<Picker>,
+    events: EventBus,
+    overlay: Option<Overlay>,
+    server: Option<Server>,
+    autosplitter: Option<Box<dyn Autosplitter>>,
+    next_autosplit: Instant,
+    // Why the autosplitter stopped, if it failed during the run
+    autosplitter_error: Option<anyhow::Error>,
+    // Whether the timer is paused for a load, which only the autosplitter may resume
+    autosplitter_paused: bool,
+    // Shared with the event listener that redraws the view after each event
+    redraw: Rc<Cell<bool>>,
 }
 
 impl Timer {
-    pub fn new(splits_file: &Path, config_path: &Path) -> anyhow::Result<Self> {
+    pub fn new(splits_file: &Path, settings: Settings) -> anyhow::Result<Self> {
+        let hotkeys = input::spawn_hotkey_thread(settings.global_hotkeys.clone());
+        Self::with_hotkeys(splits_file, settings, hotkeys)
+    }
+
+    /// A timer taking its hotkey actions from `hotkeys` instead of polling the global hotkeys in
+    /// the settings, for timers that share a polling thread.
+    pub fn with_hotkeys(
+        splits_file: &Path,
+        settings: Settings,
+        hotkeys: Receiver<ActionEvent>,
+    ) -> anyhow::Result<Self> {
         let split_file = read_split_file(splits_file).context("Failed to read splits file")?;
 
-        let settings = if config_path.exists() {
-            settings::read_settings_file(config_path).context("Failed to read settings file")?
-        } else {
-            settings::DEFAULT_SETTINGS.clone()
-        };
+        let redraw = Rc::new(Cell::new(true));
+        let mut events = EventBus::default();
+        events.subscribe_recorder(RunSaver);
+        events.subscribe(view::Redraw::new(redraw.clone()));
+        if !settings.hooks.is_empty() {
+            events.subscribe(Hooks::new(settings.hooks.clone()));
+        }
+        if !settings.sounds.is_empty() {
+            let backend = audio::get_backend(settings.audio_backend)?;
+            events.subscribe(SoundCues::new(settings.sounds.clone(), backend)?);
+        }
+        if let Some(path) = &settings.practice_log {
+            events.subscribe(PracticeLog::open(path)?);
+        }
+
+        let overlay = settings.overlay.clone().map(Overlay::new).transpose()?;
+        let server = settings.server.as_ref().map(Server::start).transpose()?;
+        let autosplitter = load_split_file_autosplitter(&split_file)?;
 
         Ok(Self {
-            device_state: DeviceState::new(),
+            hotkeys,
             renderer: Renderer::new(),
             timer_state: TimerState {
                 split_file,
                 splits: Vec::new(),
                 mode: TimerMode::Initial,
                 anim_ref_time: Instant::now(),
+                practice: false,
+                segment_practice: None,
             },
             settings,
-            prev_keys: HashSet::new(),
+            picker: None,
+            events,
+            overlay,
+            server,
+            autosplitter,
+            next_autosplit: Instant::now(),
+            autosplitter_error: None,
+            autosplitter_paused: false,
+            redraw,
         })
     }
 
-    pub fn update(&mut self, _: f32) -> anyhow::Result<bool> {
-        if read_terminal_key_chars()?.contains(&'q') {
+    /// Handles terminal keys and global hotkeys. Returns false once the timer should quit.
+    pub fn handle_input(&mut self) -> anyhow::Result<bool> {
+        let events = read_terminal_events()?;
+        let terminal_keys: Vec<KeyCode> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Key(KeyEvent {
+                    code,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) => Some(*code),
+                _ => None,
+            })
+            .collect();
+
+        // Key presses and resizes can change what's on screen without the timer running
+        if !events.is_empty() {
+            self.redraw.set(true);
+        }
+
+        if self.picker.is_some() {
+            // Hotkeys pressed while choosing a file are dropped
+            self.take_actions();
+            return self.update_picker(&terminal_keys);
+        }
+
+        if terminal_keys.contains(&KeyCode::Char('q')) {
             return Ok(false);
         }
+        if terminal_keys.contains(&KeyCode::Char('s')) {
+            self.apply_action(Action::SwitchSplitFile, Instant::now())?;
+        }
 
-        let global_keys: HashSet<Keycode> = self.device_state.get_keys().into_iter().collect();
-        let actions: Vec<Action> = global_keys
-            .iter()
-            .filter(|key| !self.prev_keys.contains(key))
-            .flat_map(|key| self.settings.global_hotkeys.get(key).copied())
-            .collect();
-        self.prev_keys = global_keys;
+        self.update()?;
+        Ok(true)
+    }
+
+    /// Applies the hotkeys, server commands and autosplitter commands that came in since the
+    /// last update, without touching the terminal.
+    pub fn update(&mut self) -> anyhow::Result<()> {
+        for event in self.take_actions() {
+            self.apply_action(event.action, event.time)?;
+        }
+        self.update_autosplitter()
+    }
+
+    fn take_actions(&mut self) -> Vec<ActionEvent> {
+        let mut actions: Vec<ActionEvent> = self.hotkeys.try_iter().collect();
+        if let Some(server) = &self.server {
+            actions.extend(server.commands());
+        }
+        if !actions.is_empty() {
+            self.redraw.set(true);
+        }
+        actions
+    }
 
-        for action in actions {
-            self.apply_action(action)?;
+    fn update_autosplitter(&mut self) -> anyhow::Result<()> {
+        let Some(autosplitter) = &mut self.autosplitter else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        if now < self.next_autosplit {
+            return Ok(());
+        }
+        self.next_autosplit = now + TICK_INTERVAL;
+
+        match autosplitter.update(&self.timer_state) {
+            Ok(commands) => {
+                for command in commands {
+                    self.apply_autosplitter_command(command, now)?;
+                }
+            }
+            Err(err) => {
+                // Keep timing by hand rather than losing the run, and report it on quitting
+                self.autosplitter = None;
+                self.autosplitter_error = Some(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// The first error from each event listener that failed, such as a hook that couldn't run.
+    pub fn take_listener_errors(&mut self) -> Vec<anyhow::Error> {
+        self.events.take_errors()
+    }
+
+    /// The error that stopped the autosplitter, if any.
+    pub fn take_autosplitter_error(&mut self) -> Option<anyhow::Error> {
+        self.autosplitter_error.take()
+    }
+
+    // Autosplitters say what happened in the game, which only means something in some modes
+    fn apply_autosplitter_command(
+        &mut self,
+        command: AutosplitterCommand,
+        time: Instant,
+    ) -> anyhow::Result<()> {
+        let action = match (command, &self.timer_state.mode) {
+            (AutosplitterCommand::Start, TimerMode::Initial) => Action::Split,
+            (AutosplitterCommand::Split, TimerMode::Running { .. }) => Action::Split,
+            (AutosplitterCommand::Reset, TimerMode::Initial) => return Ok(()),
+            (AutosplitterCommand::Reset, _) => Action::ResetAndSave,
+            (AutosplitterCommand::PauseGameTime, TimerMode::Running { .. }) => {
+                self.apply_action(Action::Pause, time)?;
+                self.autosplitter_paused = true;
+                return Ok(());
+            }
+            // A pause by hand stays paused when a load ends
+            (AutosplitterCommand::ResumeGameTime, TimerMode::Paused { .. })
+                if self.autosplitter_paused =>
+            {
+                Action::Pause
+            }
+            _ => return Ok(()),
+        };
+        self.apply_action(action, time)
+    }
+
+    /// Whether something changed that should be drawn before the next frame is due.
+    pub fn needs_redraw(&self) -> bool {
+        self.redraw.get()
+    }
+
+    /// Like `needs_redraw`, but for drawing the timer somewhere else, so the flag is cleared.
+    pub fn take_redraw(&mut self) -> bool {
+        self.redraw.replace(false)
+    }
+
+    pub fn state(&self) -> &TimerState {
+        &self.timer_state
+    }
+
+    /// Time until the next frame, which is longer while nothing on screen is moving.
+    pub fn frame_interval(&self) -> Duration {
+        let animated = self.picker.is_none() && view::is_animated(&self.timer_state);
+        let fps = if self.settings.adaptive_fps && !animated {
+            self.settings.idle_fps.min(self.settings.fps)
+        } else {
+            self.settings.fps
+        };
+        Duration::from_secs_f32(1.0 / fps as f32)
+    }
+
+    pub fn render(&mut self) -> anyhow::Result<()> {
+        self.redraw.set(false);
+
+        if let Some(picker) = &self.picker {
+            let block = picker.render(self.settings.theme);
+            self.renderer.render(&block)?;
+            return Ok(());
         }
 
         let bg_color = if self.settings.draw_background {
@@ -70,154 +263,251 @@ impl Timer {
         self.renderer
             .set_default_colors(parse_color(self.settings.theme.normal_text), bg_color);
 
-        let block = view::render_view(&self.timer_state, self.settings.theme);
+        let block = view::render_view(&self.timer_state, &self.settings);
         self.renderer.render(&block)?;
-        Ok(true)
+
+        if let Some(overlay) = &mut self.overlay {
+            // The overlay is only for show, so failing to write it shouldn't stop the run
+            let _ = overlay.update(&self.timer_state);
+        }
+        if let Some(server) = &mut self.server {
+            server.publish(&self.timer_state);
+        }
+        Ok(())
+    }
+
+    pub fn subscribe(&mut self, listener: impl EventListener + 'static) {
+        self.events.subscribe(listener);
     }
 
-    pub fn apply_action(&mut self, action: Action) -> anyhow::Result<()> {
+    pub fn set_practice(&mut self, practice: bool) {
+        self.timer_state.practice = practice;
+        self.redraw.set(true);
+    }
+
+    /// Times only the splits from position `from` to `to`, counting from 1 and defaulting to the
+    /// first and last split.
+    pub fn set_segment_practice(
+        &mut self,
+        from: Option<usize>,
+        to: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let count = self.timer_state.split_file.split_names.len();
+        let from = from.unwrap_or(1);
+        let to = to.unwrap_or(count);
+        if from == 0 || from > to || to > count {
+            return Err(anyhow!(
+                "Splits to practice must be between 1 and {}, in order",
+                count
+            ));
+        }
+        self.timer_state.segment_practice = Some(from - 1..to);
+        self.redraw.set(true);
+        Ok(())
+    }
+
+    /// Applies an action as of `time`, when its key was pressed.
+    pub fn apply_action(&mut self, action: Action, time: Instant) -> anyhow::Result<()> {
+        // Works at any point, so a run that's going badly can still be kept out of the file
+        if action == Action::TogglePractice {
+            self.set_practice(!self.timer_state.practice);
+            return Ok(());
+        }
+
         match self.timer_state.mode {
-            #[allow(clippy::single_match)]
             TimerMode::Initial => match action {
                 Action::Split => {
+                    let splits = self.segment_practice_splits();
+                    let offset = splits.last().copied().flatten().unwrap_or_default();
+                    self.timer_state.splits = splits;
                     self.timer_state.mode = TimerMode::Running {
-                        start_time: Instant::now(),
+                        start_time: time.checked_sub(offset).unwrap_or(time),
                     };
+                    self.emit(TimerEvent::RunStarted)?;
                 }
+                Action::SwitchSplitFile => self.open_picker()?,
                 _ => {}
             },
             TimerMode::Paused { elapsed_at_pause } => match action {
                 Action::Pause => {
-                    let new_start_time = Instant::now() - elapsed_at_pause;
+                    self.autosplitter_paused = false;
+                    let new_start_time = time - elapsed_at_pause;
                     self.timer_state.mode = TimerMode::Running {
                         start_time: new_start_time,
                     };
+                    self.emit(TimerEvent::Resumed)?;
                 }
-                Action::ResetAndSave => {
-                    self.timer_state.split_file.attempts += 1;
-                    self.save_golds()?;
-                    self.reset_to_initial_mode();
-                }
-                Action::ResetAndDelete => {
-                    self.reset_to_initial_mode();
-                }
+                Action::ResetAndSave => self.emit(TimerEvent::Reset { saved: true })?,
+                Action::ResetAndDelete => self.emit(TimerEvent::Reset { saved: false })?,
                 _ => {}
             },
             TimerMode::Running { start_time } => match action {
                 Action::Split => {
-                    let elapsed = start_time.elapsed();
+                    let elapsed = time.saturating_duration_since(start_time);
                     self.timer_state.splits.push(Some(elapsed));
-                    if self.timer_state.splits.len()
-                        == self.timer_state.split_file.split_names.len()
-                    {
+                    if self.timer_state.splits.len() == self.timer_state.end_split() {
                         self.timer_state.mode = TimerMode::Finished { start_time };
                     }
+                    self.emit_split_events()?;
                 }
                 Action::UndoSplit => {
-                    if self.timer_state.splits.is_empty() {
+                    if self.timer_state.splits.len() <= self.timer_state.first_split() {
                         self.reset_to_initial_mode();
                     } else {
                         self.timer_state.splits.pop();
                     }
                 }
                 Action::DeleteSplit => {
-                    if !self.timer_state.splits.is_empty() {
+                    if self.timer_state.splits.len() > self.timer_state.first_split() {
                         let len = self.timer_state.splits.len();
                         self.timer_state.splits[len - 1] = None;
                     }
                 }
-                Action::ResetAndSave => {
-                    self.timer_state.split_file.attempts += 1;
-                    self.save_golds()?; // Also saves attempts
-                    self.reset_to_initial_mode();
-                }
-                Action::ResetAndDelete => {
-                    self.reset_to_initial_mode();
-                }
+                Action::ResetAndSave => self.emit(TimerEvent::Reset { saved: true })?,
+                Action::ResetAndDelete => self.emit(TimerEvent::Reset { saved: false })?,
                 Action::Pause => {
+                    let elapsed = time.saturating_duration_since(start_time);
                     self.timer_state.mode = TimerMode::Paused {
-                        elapsed_at_pause: start_time.elapsed(),
+                        elapsed_at_pause: elapsed,
                     };
+                    self.emit(TimerEvent::Paused { elapsed })?;
                 }
+                Action::SwitchSplitFile | Action::TogglePractice => {}
             },
             TimerMode::Finished { start_time } => match action {
                 Action::UndoSplit => {
                     self.timer_state.splits.pop();
                     self.timer_state.mode = TimerMode::Running { start_time };
                 }
-                Action::ResetAndSave => {
-                    self.timer_state.split_file.attempts += 1;
-                    self.timer_state.split_file.completed += 1;
-                    self.save_golds()?; // Also saves attempts/completed
-                    self.save_personal_best()?;
-                    self.reset_to_initial_mode();
-                }
-                Action::ResetAndDelete => {
-                    self.reset_to_initial_mode();
-                }
+                Action::ResetAndSave => self.emit(TimerEvent::Reset { saved: true })?,
+                Action::ResetAndDelete => self.emit(TimerEvent::Reset { saved: false })?,
                 _ => {}
             },
         }
         Ok(())
     }
 
-    fn reset_to_initial_mode(&mut self) {
-        self.timer_state.mode = TimerMode::Initial;
-        self.timer_state.splits.clear();
+    fn emit_split_events(&mut self) -> anyhow::Result<()> {
+        for event in split_events(&self.timer_state) {
+            self.emit(event)?;
+        }
+        Ok(())
     }
 
-    fn save_golds(&mut self) -> anyhow::Result<()> {
-        let run_summary = get_run_summary(&self.timer_state);
-
-        for (i, seg) in run_summary.iter().enumerate() {
-            let file_golds = &mut self.timer_state.split_file.golds;
-            file_golds[i] = seg.gold.as_ref().map(|&g| Gold { duration: g });
+    fn emit(&mut self, event: TimerEvent) -> anyhow::Result<()> {
+        let result = self.events.emit(&event, &mut self.timer_state);
+        // The run is over even if it couldn't be saved, and keeping it would record it again on
+        // the next reset
+        if let TimerEvent::Reset { .. } = event {
+            self.reset_to_initial_mode();
         }
+        result
+    }
 
-        write_split_file(&self.timer_state.split_file)?;
-
+    fn open_picker(&mut self) -> anyhow::Result<()> {
+        let path = self.timer_state.split_file.path();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        self.picker = Some(Picker::new(dir)?.with_selected(path));
         Ok(())
     }
 
-    fn save_personal_best(&mut self) -> anyhow::Result<()> {
-        let splits = &self.timer_state.splits;
-        let curr_time = splits.last().unwrap().unwrap();
-
-        let should_save = match &self.timer_state.split_file.personal_best {
-            Some(pb) => {
-                let pb_time = pb.splits.last().unwrap().as_ref().unwrap().time;
-                curr_time < pb_time
+    fn update_picker(&mut self, keys: &[KeyCode]) -> anyhow::Result<bool> {
+        for &key in keys {
+            let picker = self.picker.as_mut().unwrap();
+            match picker.handle_key(key) {
+                // A file that doesn't load leaves the current one open, rather than ending the
+                // session over a bad file in the directory
+                Some(PickerChoice::Open(path)) => match self.open_split_file(&path) {
+                    Ok(()) => {
+                        self.picker = None;
+                        return Ok(true);
+                    }
+                    Err(err) => self
+                        .picker
+                        .as_mut()
+                        .unwrap()
+                        .set_error(format!("{:#}", err)),
+                },
+                Some(PickerChoice::Cancel) => {
+                    self.picker = None;
+                    return Ok(true);
+                }
+                None => {}
             }
-            None => true, 
+        }
+        Ok(true)
+    }
+
+    fn open_split_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let split_file = read_split_file(path).context("Failed to read splits file")?;
+        self.autosplitter = load_split_file_autosplitter(&split_file)?;
+        self.timer_state = TimerState {
+            split_file,
+            splits: Vec::new(),
+            mode: TimerMode::Initial,
+            anim_ref_time: Instant::now(),
+            practice: self.timer_state.practice,
+            // The splits to practice were chosen for the old file
+            segment_practice: None,
         };
+        Ok(())
+    }
 
-        if should_save {
-            let pb = PersonalBest {
-                attempt: self.timer_state.split_file.attempts,
-                splits: splits.iter().map(|s| s.map(|dur| Split { time: dur })).collect(),
-            };
-            self.timer_state.split_file.personal_best = Some(pb);
+    // Segment practice starts partway through a run, as if it had matched the PB up to there.
+    // Where the PB skipped splits, the golds since its last time before there fill the gap, so
+    // deltas against its later splits still mean something.
+    fn segment_practice_splits(&self) -> Vec<Option<Duration>> {
+        let first = self.timer_state.first_split();
+        if first == 0 {
+            return Vec::new();
         }
-        write_split_file(&self.timer_state.split_file)?;
 
-        Ok(())
+        let split_file = &self.timer_state.split_file;
+        let (gap_start, pb_time) = split_file
+            .personal_best
+            .as_ref()
+            .and_then(|pb| {
+                pb.splits[..first]
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .find_map(|(i, split)| Some((i + 1, split.as_ref()?.time)))
+            })
+            .unwrap_or_default();
+        let gold_sum = split_file.golds[gap_start..first]
+            .iter()
+            .map(|gold| gold.as_ref().map(|gold| gold.duration))
+            .sum::<Option<Duration>>();
+
+        let mut splits = vec![None; first];
+        splits[first - 1] = Some(pb_time + gold_sum.unwrap_or_default());
+        splits
+    }
+
+    fn reset_to_initial_mode(&mut self) {
+        self.autosplitter_paused = false;
+        self.timer_state.mode = TimerMode::Initial;
+        self.timer_state.splits.clear();
     }
 }
 
-fn read_terminal_key_chars() -> anyhow::Result<Vec<char>> {
-    let mut input_chars = Vec::new();
+fn load_split_file_autosplitter(
+    split_file: &SplitFile,
+) -> anyhow::Result<Option<Box<dyn Autosplitter>>> {
+    split_file
+        .autosplitter
+        .as_ref()
+        .map(|config| load_autosplitter(config, split_file))
+        .transpose()
+}
 
+fn read_terminal_events() -> anyhow::Result<Vec<Event>> {
+    let mut events = Vec::new();
     while event::poll(Duration::from_secs(0))? {
-        if let Event::Key(KeyEvent {
-            code: KeyCode::Char(c),
-            kind: KeyEventKind::Press,
-            modifiers: _,
-            state: _,
-        }) = event::read()?
-        {
-            input_chars.push(c);
-        }
+        events.push(event::read()?);
     }
-
-    Ok(input_chars)
+    Ok(events)
 }
@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::time::{Duration, Instant};
 
 use crate::split_file::SplitFile;
@@ -15,4 +16,24 @@ pub struct TimerState {
     pub mode: TimerMode,
     pub splits: Vec<Option<Duration>>,
     pub anim_ref_time: Instant,
+    // Runs in practice mode are timed and compared, but never saved to the splits file
+    pub practice: bool,
+    // Splits being practiced on their own, when not running the whole run
+    pub segment_practice: Option<Range<usize>>,
+}
+
+impl TimerState {
+    /// Index of the first split that's actually run.
+    pub fn first_split(&self) -> usize {
+        self.segment_practice
+            .as_ref()
+            .map_or(0, |range| range.start)
+    }
+
+    /// Number of splits done once the run is finished.
+    pub fn end_split(&self) -> usize {
+        self.segment_practice
+            .as_ref()
+            .map_or(self.split_file.split_names.len(), |range| range.end)
+    }
 }
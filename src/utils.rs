@@ -1,6 +1,9 @@
+use std::str::FromStr;
+use std::sync::LazyLock;
 use std::time::Duration;
 
 use crossterm::style::Color;
+use regex::Regex;
 
 use crate::timer_state::{TimerMode, TimerState};
 
@@ -60,6 +63,32 @@ pub fn format_duration(duration: Duration, ms_digits: u32, sign: Sign, prefix: P
     String::from(&s[..(s.len() - (3 - ms_digits as usize))])
 }
 
+static DURATION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(?:(?:(\d+):)?(\d+):)?(\d+):)?(\d+)(?:\.(\d{1,3}))?$").unwrap()
+});
+
+/// Parses a duration in the `D:H:MM:SS.mmm` format written by `format_duration`, where all but
+/// the seconds are optional.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let caps = DURATION_REGEX.captures(value)?;
+    let get_or_zero = |idx| match caps.get(idx) {
+        Some(d) => u64::from_str(d.as_str()).ok(),
+        None => Some(0),
+    };
+
+    let days = get_or_zero(1)?;
+    let hours = get_or_zero(2)?;
+    let minutes = get_or_zero(3)?;
+    let seconds = get_or_zero(4)?;
+    let milliseconds = match caps.get(5) {
+        Some(d) => format!("{:0<3}", d.as_str()).parse().unwrap(),
+        None => 0,
+    };
+
+    let secs = seconds + (minutes * 60) + (hours * 60 * 60) + (days * 60 * 60 * 24);
+    Some(Duration::from_secs(secs) + Duration::from_millis(milliseconds))
+}
+
 pub fn parse_color(color_hex: &str) -> Color {
     let color = u32::from_str_radix(color_hex.trim_start_matches('#'), 16).unwrap_or(0);
     Color::Rgb {
@@ -87,6 +116,18 @@ pub struct SegSummary {
 
     pub gold: Option<Duration>,
     pub is_gold_new: bool,
+
+    // Time that could be saved this segment by matching the gold
+    pub possible_save: Option<Duration>,
+}
+
+pub fn get_elapsed(timer: &TimerState) -> Duration {
+    match timer.mode {
+        TimerMode::Initial => Duration::from_secs(0),
+        TimerMode::Running { start_time } => start_time.elapsed(),
+        TimerMode::Paused { elapsed_at_pause } => elapsed_at_pause,
+        TimerMode::Finished { start_time: _ } => timer.splits.last().unwrap().unwrap(),
+    }
 }
 
 pub fn get_run_summary(timer: &TimerState) -> Vec<SegSummary> {
@@ -159,6 +200,14 @@ pub fn get_run_summary(timer: &TimerState) -> Vec<SegSummary> {
         }
     }
 
+    // Splits skipped by segment practice weren't run, so there's nothing to show for them
+    for seg in &mut summary[..timer.first_split().min(timer.splits.len())] {
+        seg.live_split = None;
+        seg.live_seg = None;
+        seg.live_delta = None;
+        seg.gained = None;
+    }
+
     // Calculate golds
     #[allow(clippy::needless_range_loop)]
     for i in 0..timer.splits.len() {
@@ -185,5 +234,57 @@ pub fn get_run_summary(timer: &TimerState) -> Vec<SegSummary> {
         summary[i].is_gold_new = false;
     }
 
+    // Calculate possible time saves
+    for seg in summary.iter_mut() {
+        if let (Some(pb_seg), Some(gold)) = (seg.pb_seg, seg.gold) {
+            seg.possible_save = Some(pb_seg.saturating_sub(gold));
+        }
+    }
+
     summary
 }
+
+/// Sums the golds that exist, also returning how many segments have no gold.
+pub fn get_sum_of_best(summary: &[SegSummary]) -> (Duration, usize) {
+    let sob = summary.iter().flat_map(|seg| seg.gold).sum();
+    let missing = summary.iter().filter(|seg| seg.gold.is_none()).count();
+    (sob, missing)
+}
+
+pub fn get_total_possible_save(summary: &[SegSummary]) -> Option<Duration> {
+    if summary.iter().all(|seg| seg.possible_save.is_none()) {
+        return None;
+    }
+    Some(summary.iter().flat_map(|seg| seg.possible_save).sum())
+}
+
+/// The fastest final time still achievable this run, assuming every remaining segment is a gold.
+pub fn get_best_possible_time(timer: &TimerState, summary: &[SegSummary]) -> Option<Duration> {
+    let curr_idx = match timer.mode {
+        TimerMode::Initial => {
+            let (sob, missing) = get_sum_of_best(summary);
+            return if missing == 0 { Some(sob) } else { None };
+        }
+        TimerMode::Finished { .. } => return Some(get_elapsed(timer)),
+        TimerMode::Running { .. } | TimerMode::Paused { .. } => timer.splits.len(),
+    };
+
+    let elapsed = get_elapsed(timer);
+    let prev_split = if curr_idx == 0 {
+        Some(Duration::from_secs(0))
+    } else {
+        timer.splits[curr_idx - 1]
+    };
+
+    // The current segment can't finish faster than its gold
+    let curr_split = match (prev_split, summary[curr_idx].gold) {
+        (Some(prev_split), Some(gold)) => elapsed.max(prev_split + gold),
+        _ => elapsed,
+    };
+
+    let mut remaining = Duration::from_secs(0);
+    for seg in &summary[curr_idx + 1..] {
+        remaining += seg.gold?;
+    }
+    Some(curr_split + remaining)
+}
@@ -1,33 +1,37 @@
+use std::cell::Cell;
+use std::rc::Rc;
 use std::time::Duration;
 
 use crossterm::style::{Attribute, Color};
 
 use crate::{
     bigtext::get_big_text,
+    events::{EventListener, TimerEvent},
     rotty::{Block, Image, TextAlign},
-    settings::Theme,
+    settings::{Column, Settings, Theme},
     timer_state::{TimerMode, TimerState},
-    utils::{format_duration, get_run_summary, parse_color, Prefix, SegSummary, Sign},
+    utils::{
+        format_duration, get_best_possible_time, get_elapsed, get_run_summary, get_sum_of_best,
+        get_total_possible_save, parse_color, Prefix, SegSummary, Sign,
+    },
 };
 
-static TIMER_WIDTH: u16 = 48;
+static MIN_TIMER_WIDTH: u16 = 48;
 static COL_WIDTH: u16 = 12;
-pub fn render_view(timer: &TimerState, theme: &Theme) -> Block {
+pub fn render_view(timer: &TimerState, settings: &Settings) -> Block {
+    let theme = settings.theme;
     let summary = get_run_summary(timer);
+    let elapsed = get_elapsed(timer);
 
-    let elapsed = match timer.mode {
-        TimerMode::Initial => Duration::from_secs(0),
-        TimerMode::Running { start_time } => start_time.elapsed(),
-        TimerMode::Paused { elapsed_at_pause } => elapsed_at_pause,
-        TimerMode::Finished { start_time: _ } => timer.splits.last().unwrap().unwrap(),
-    };
+    // Name column plus one per configured column
+    let width = MIN_TIMER_WIDTH.max(COL_WIDTH * (settings.columns.len() as u16 + 1));
 
     let title = &timer.split_file.title;
     let category = &timer.split_file.category;
-    let title_block = Image::new(title, TIMER_WIDTH, TextAlign::Center)
+    let title_block = Image::new(title, width, TextAlign::Center)
         .attr(Attribute::Bold)
         .build();
-    let category_block = Image::new(category, TIMER_WIDTH, TextAlign::Center)
+    let category_block = Image::new(category, width, TextAlign::Center)
         .attr(Attribute::Bold)
         .build();
 
@@ -35,32 +39,48 @@ pub fn render_view(timer: &TimerState, theme: &Theme) -> Block {
         "{}/{}",
         timer.split_file.completed, timer.split_file.attempts
     );
-    let attempts_block = Image::new(&attempts_text, TIMER_WIDTH, TextAlign::Right).build();
-
-    let spacer_block = Image::new(
-        &" ".repeat(TIMER_WIDTH as usize),
-        TIMER_WIDTH,
-        TextAlign::Left,
-    )
-    .build();
-
-    let headers = ["", "Delta", "Segment", "Split"].map(|h| {
-        Image::new(h, COL_WIDTH, TextAlign::Right)
-            .fg_color(parse_color(theme.label_text))
-            .build()
-    });
+    let practice_label = match &timer.segment_practice {
+        _ if timer.practice => Some("Practice".to_string()),
+        Some(range) if range.len() == 1 => Some(format!("Segment {}", range.end)),
+        Some(range) => Some(format!("Segments {}-{}", range.start + 1, range.end)),
+        None => None,
+    };
+    let attempts_block = if let Some(label) = practice_label {
+        let label_width = width / 2;
+        Block::hcat([
+            Image::new(&label, label_width, TextAlign::Left)
+                .fg_color(parse_color(theme.highlight))
+                .attr(Attribute::Bold)
+                .build(),
+            Image::new(&attempts_text, width - label_width, TextAlign::Right).build(),
+        ])
+    } else {
+        Image::new(&attempts_text, width, TextAlign::Right).build()
+    };
+
+    let spacer_block = Image::new(&" ".repeat(width as usize), width, TextAlign::Left).build();
+
+    let headers = std::iter::once("")
+        .chain(settings.columns.iter().map(|col| match col {
+            Column::Delta => "Delta",
+            Column::Segment => "Segment",
+            Column::Split => "Split",
+            Column::PossibleTimeSave => "Time Save",
+            Column::Resets => "Resets",
+        }))
+        .map(|h| {
+            Image::new(h, COL_WIDTH, TextAlign::Right)
+                .fg_color(parse_color(theme.label_text))
+                .build()
+        });
     let header_row = Block::hcat(headers);
 
-    let line_sep = Image::new(
-        &"─".repeat(TIMER_WIDTH as usize),
-        TIMER_WIDTH,
-        TextAlign::Left,
-    )
-    .fg_color(parse_color(theme.label_text))
-    .build();
+    let line_sep = Image::new(&"─".repeat(width as usize), width, TextAlign::Left)
+        .fg_color(parse_color(theme.label_text))
+        .build();
 
     let split_rows: Vec<Block> = (0..timer.split_file.split_names.len())
-        .map(|i| get_split_row(timer, i as u32, theme, &summary))
+        .map(|i| get_split_row(timer, i as u32, settings, &summary, width))
         .collect();
 
     let mut sections = vec![
@@ -75,26 +95,61 @@ pub fn render_view(timer: &TimerState, theme: &Theme) -> Block {
     sections.extend([
         line_sep,
         spacer_block.clone(),
-        get_big_timer(timer, theme, &summary, elapsed),
+        get_big_timer(timer, theme, &summary, elapsed, width),
         spacer_block,
-        get_prev_segment_block(timer, theme, &summary),
-        get_sum_of_best_block(&summary),
+        get_prev_segment_block(timer, theme, &summary, width),
+        get_sum_of_best_block(&summary, width),
+        get_possible_save_block(&summary, width),
+        get_best_possible_time_block(timer, &summary, width),
     ]);
     Block::vcat(sections)
 }
 
+/// Asks for the view to be drawn again after every timer event, since they all change it.
+pub struct Redraw {
+    redraw: Rc<Cell<bool>>,
+}
+
+impl Redraw {
+    pub fn new(redraw: Rc<Cell<bool>>) -> Self {
+        Self { redraw }
+    }
+}
+
+impl EventListener for Redraw {
+    fn on_event(&mut self, _event: &TimerEvent, _timer: &TimerState) -> anyhow::Result<()> {
+        self.redraw.set(true);
+        Ok(())
+    }
+}
+
+/// Whether the view changes from frame to frame, either from the running clock or a rainbow.
+pub fn is_animated(timer: &TimerState) -> bool {
+    match timer.mode {
+        TimerMode::Initial => false,
+        TimerMode::Running { .. } => true,
+        TimerMode::Paused { .. } | TimerMode::Finished { .. } => {
+            let summary = get_run_summary(timer);
+            let finished_ahead = matches!(timer.mode, TimerMode::Finished { .. })
+                && summary[timer.splits.len() - 1].live_delta_neg;
+            finished_ahead || summary.iter().any(|seg| seg.is_gold_new)
+        }
+    }
+}
+
 fn get_big_timer(
     timer: &TimerState,
     theme: &Theme,
     summary: &[SegSummary],
     elapsed: Duration,
+    width: u16,
 ) -> Block {
     let color = match timer.mode {
         TimerMode::Initial => parse_color(theme.ahead_gain),
         TimerMode::Running { .. } => get_delta_color(timer.splits.len() as u32, theme, summary),
         TimerMode::Paused { .. } => parse_color(theme.paused_text),
         TimerMode::Finished { .. } => {
-            if summary[summary.len() - 1].live_delta_neg {
+            if summary[timer.splits.len() - 1].live_delta_neg {
                 get_rainbow_color(timer)
             } else {
                 parse_color(theme.behind_lose)
@@ -108,52 +163,69 @@ fn get_big_timer(
         Sign::Positive,
         Prefix::NoneOrMinus,
     ))
-    .left_pad(TIMER_WIDTH)
+    .left_pad(width)
     .fg_color(color)
 }
 
-fn get_split_row(timer: &TimerState, idx: u32, theme: &Theme, summary: &[SegSummary]) -> Block {
+fn get_split_row(
+    timer: &TimerState,
+    idx: u32,
+    settings: &Settings,
+    summary: &[SegSummary],
+    width: u16,
+) -> Block {
+    let theme = settings.theme;
     let split_name = &timer.split_file.split_names[idx as usize];
     let name_col = Image::new(split_name, COL_WIDTH, TextAlign::Left).build();
 
-    // Build segment text
-    let seg_dur = if (idx as usize) < timer.splits.len() {
-        summary[idx as usize].live_seg
-    } else {
-        summary[idx as usize].pb_seg
-    };
-    let seg_text = match seg_dur {
-        Some(seg_dur) => format_duration(seg_dur, 2, Sign::Positive, Prefix::NoneOrMinus),
-        None => "-".to_string(),
-    };
-
-    // Build split text
-    let split_dur = if (idx as usize) < timer.splits.len() {
-        summary[idx as usize].live_split
-    } else {
-        summary[idx as usize].pb_split
-    };
-    let split_text = match split_dur {
-        Some(split_dur) => format_duration(split_dur, 2, Sign::Positive, Prefix::NoneOrMinus),
-        None => "-".to_string(),
-    };
-
-    let seg_col = Image::new(&seg_text, COL_WIDTH, TextAlign::Right).build();
-    let split_col = Image::new(&split_text, COL_WIDTH, TextAlign::Right).build();
-    let delta_col = get_delta_block(timer, idx, theme, summary);
+    let cols = settings.columns.iter().map(|col| match col {
+        Column::Delta => get_delta_block(timer, idx, theme, summary),
+        Column::Segment => {
+            let seg_dur = if (idx as usize) < timer.splits.len() {
+                summary[idx as usize].live_seg
+            } else {
+                summary[idx as usize].pb_seg
+            };
+            get_duration_col(seg_dur)
+        }
+        Column::Split => {
+            let split_dur = if (idx as usize) < timer.splits.len() {
+                summary[idx as usize].live_split
+            } else {
+                summary[idx as usize].pb_split
+            };
+            get_duration_col(split_dur)
+        }
+        Column::PossibleTimeSave => get_duration_col(summary[idx as usize].possible_save),
+        Column::Resets => get_resets_col(timer, idx),
+    });
 
     let running = matches!(timer.mode, TimerMode::Running { start_time: _ });
-    let mut bg_image = Image::new(
-        &" ".repeat(TIMER_WIDTH as usize),
-        TIMER_WIDTH,
-        TextAlign::Left,
-    );
+    let mut bg_image = Image::new(&" ".repeat(width as usize), width, TextAlign::Left);
     if running && idx as usize == timer.splits.len() {
         bg_image = bg_image.bg_color(parse_color(theme.highlight));
     }
     let bg = bg_image.build();
 
-    bg.stack(Block::hcat([name_col, delta_col, seg_col, split_col]))
+    bg.stack(Block::hcat(std::iter::once(name_col).chain(cols)))
+}
+
+fn get_duration_col(dur: Option<Duration>) -> Block {
+    let text = match dur {
+        Some(dur) => format_duration(dur, 2, Sign::Positive, Prefix::NoneOrMinus),
+        None => "-".to_string(),
+    };
+    Image::new(&text, COL_WIDTH, TextAlign::Right).build()
+}
+
+fn get_resets_col(timer: &TimerState, idx: u32) -> Block {
+    let resets = timer.split_file.resets[idx as usize];
+    let reached = timer.split_file.runs_reaching(idx as usize);
+    let text = match reached.and_then(|reached| (resets * 100).checked_div(reached)) {
+        Some(percent) => format!("{} ({}%)", resets, percent),
+        None => "-".to_string(),
+    };
+    Image::new(&text, COL_WIDTH, TextAlign::Right).build()
 }
 
 fn get_delta_color(idx: u32, theme: &Theme, summary: &[SegSummary]) -> Color {
@@ -219,7 +291,12 @@ fn get_delta_block(timer: &TimerState, idx: u32, theme: &Theme, summary: &[SegSu
     }
 }
 
-fn get_prev_segment_block(timer: &TimerState, theme: &Theme, summary: &[SegSummary]) -> Block {
+fn get_prev_segment_block(
+    timer: &TimerState,
+    theme: &Theme,
+    summary: &[SegSummary],
+    width: u16,
+) -> Block {
     let gained_dur = if timer.splits.is_empty() {
         None
     } else {
@@ -241,24 +318,49 @@ fn get_prev_segment_block(timer: &TimerState, theme: &Theme, summary: &[SegSumma
         s = "-".to_string();
     };
 
-    let label_col = Image::new("Previous Segment", TIMER_WIDTH / 2, TextAlign::Left).build();
-    let prev_seg_col = Image::new(&s, TIMER_WIDTH - TIMER_WIDTH / 2, TextAlign::Right)
+    let label_col = Image::new("Previous Segment", width / 2, TextAlign::Left).build();
+    let prev_seg_col = Image::new(&s, width - width / 2, TextAlign::Right)
         .fg_color(color)
         .build();
     label_col.horiz(prev_seg_col)
 }
 
-fn get_sum_of_best_block(summary: &[SegSummary]) -> Block {
-    let sob_text = if summary.iter().all(|seg| seg.gold.is_some()) {
-        let sob = summary.iter().map(|seg| seg.gold.as_ref().unwrap()).sum();
-        format_duration(sob, 2, Sign::Positive, Prefix::NoneOrMinus)
-    } else {
-        "-".to_string()
+fn get_sum_of_best_block(summary: &[SegSummary], width: u16) -> Block {
+    // Segments without a gold are skipped, and the partial sum is marked with '*'
+    let sob_text = match get_sum_of_best(summary) {
+        (_, missing) if missing == summary.len() => "-".to_string(),
+        (sob, 0) => format_duration(sob, 2, Sign::Positive, Prefix::NoneOrMinus),
+        (sob, _) => format!(
+            "{}*",
+            format_duration(sob, 2, Sign::Positive, Prefix::NoneOrMinus)
+        ),
+    };
+
+    get_labeled_row("Sum of Best Segments", &sob_text, width)
+}
+
+fn get_possible_save_block(summary: &[SegSummary], width: u16) -> Block {
+    let save_text = match get_total_possible_save(summary) {
+        Some(save) => format_duration(save, 2, Sign::Positive, Prefix::NoneOrMinus),
+        None => "-".to_string(),
+    };
+
+    get_labeled_row("Possible Time Save", &save_text, width)
+}
+
+fn get_best_possible_time_block(timer: &TimerState, summary: &[SegSummary], width: u16) -> Block {
+    let best_text = match get_best_possible_time(timer, summary) {
+        Some(best) => format_duration(best, 2, Sign::Positive, Prefix::NoneOrMinus),
+        None => "-".to_string(),
     };
 
-    let label_col = Image::new("Sum of Best Segments", TIMER_WIDTH / 2, TextAlign::Left).build();
-    let sob_col = Image::new(&sob_text, TIMER_WIDTH - TIMER_WIDTH / 2, TextAlign::Right).build();
-    label_col.horiz(sob_col)
+    get_labeled_row("Best Possible Time", &best_text, width)
+}
+
+fn get_labeled_row(label: &str, value: &str, width: u16) -> Block {
+    let label_col = Image::new(label, width / 2, TextAlign::Left).build();
+    let value_col = Image::new(value, width - width / 2, TextAlign::Right).build();
+    label_col.horiz(value_col)
 }
 
 fn hsl_to_color(h: f64, s: f64, l: f64) -> Color {